@@ -0,0 +1,70 @@
+//! Memory-mapped against buffered counting of regular files, and both
+//! against the original implementation that read whole files into memory.
+//!
+//! Run with `cargo bench`; pass `-- --quick` for a faster, rougher run.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use ccwc::{count_path, CountOptions};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// From `MMAP_THRESHOLD` up to a large log file
+const SIZES: [usize; 3] = [1024 * 1024, 4 * 1024 * 1024, 64 * 1024 * 1024];
+
+fn sample_file(size: usize) -> tempfile::NamedTempFile {
+    let line = "2024-05-01T12:00:00Z INFO request handled in 12ms path=/api/v1/items?page=3 status=200\n";
+    let content: Vec<u8> = line.bytes().cycle().take(size).collect();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&content).unwrap();
+    file
+}
+
+/// How ccwc counted before it streamed its input: read the whole file, then
+/// count a lossily decoded copy
+fn baseline(path: &Path, options: &CountOptions) -> (usize, usize, usize) {
+    let bytes = fs::read(path).unwrap();
+    let content = String::from_utf8_lossy(&bytes);
+    let lines = content.lines().count();
+    let words = if options.words { content.split_whitespace().count() } else { 0 };
+    (lines, words, bytes.len())
+}
+
+fn bench_reader(c: &mut Criterion, name: &str, options: CountOptions) {
+    let mut group = c.benchmark_group(name);
+
+    for size in SIZES {
+        let file = sample_file(size);
+        group.throughput(Throughput::Bytes(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("baseline", size), &file, |b, file| {
+            b.iter(|| baseline(file.path(), &options))
+        });
+
+        for (reader, mmap) in [("buffered", false), ("mmap", true)] {
+            let options = CountOptions { mmap, ..options.clone() };
+            group.bench_with_input(BenchmarkId::new(reader, size), &file, |b, file| {
+                b.iter(|| count_path(file.path(), options.clone()).unwrap())
+            });
+        }
+    }
+
+    group.finish();
+}
+
+/// `-l`: newlines are counted on raw bytes, so copying them out of the page
+/// cache is a large part of the work
+fn lines(c: &mut Criterion) {
+    let options = CountOptions { words: false, chars: false, max_line_length: false, ..CountOptions::default() };
+    bench_reader(c, "lines", options);
+}
+
+/// The counts printed without options, `-lwc`, dominated by decoding
+fn default_counts(c: &mut Criterion) {
+    let options = CountOptions { chars: false, max_line_length: false, ..CountOptions::default() };
+    bench_reader(c, "default", options);
+}
+
+criterion_group!(benches, lines, default_counts);
+criterion_main!(benches);
@@ -0,0 +1,349 @@
+use crate::language::Language;
+
+/// What a single line of source code consists of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Blank,
+    Comment,
+    Code,
+}
+
+/// Lines longer than this are classified before they end, all but the last
+/// `LOOKAHEAD` bytes, which may hold the start of a marker or literal
+const MAX_LINE: usize = 4096;
+const LOOKAHEAD: usize = 64;
+
+/// Sorts source lines into blank, comment and code lines.
+///
+/// Lines are collected from decoded characters and classified once complete,
+/// or in parts once they grow past `MAX_LINE`, so that minified code or a file
+/// without newlines is not held in memory. Block comments and strings may span
+/// several lines, so the classifier remembers whether the next line starts
+/// inside one of them.
+#[derive(Debug, Clone)]
+pub struct LineClassifier {
+    language: &'static Language,
+    // The part of the current line not classified yet
+    line: String,
+    comment_depth: usize,
+    // Start and end marker of the block comment being skipped
+    block: (&'static str, &'static str),
+    open_quote: Option<&'static str>,
+    // Number of `#`s closing the raw string being skipped
+    open_raw_string: Option<usize>,
+    // What the current line held so far
+    started: bool,
+    blank: bool,
+    has_code: bool,
+    has_comment: bool,
+    // The rest of the current line is a line comment
+    in_line_comment: bool,
+    // The classified part of the line ended in the middle of a word
+    mid_word: bool,
+}
+
+impl LineClassifier {
+    pub fn new(language: &'static Language) -> Self {
+        LineClassifier {
+            language,
+            line: String::new(),
+            comment_depth: 0,
+            block: ("", ""),
+            open_quote: None,
+            open_raw_string: None,
+            started: false,
+            blank: true,
+            has_code: false,
+            has_comment: false,
+            in_line_comment: false,
+            mid_word: false,
+        }
+    }
+
+    /// Feed one character, returning the kind of the line it completes
+    pub fn push(&mut self, c: char) -> Option<LineKind> {
+        if c == '\n' {
+            return Some(self.complete_line());
+        }
+
+        self.started = true;
+        self.blank &= c.is_whitespace();
+        if !self.in_line_comment {
+            self.line.push(c);
+            if self.line.len() > MAX_LINE {
+                self.classify_part(LOOKAHEAD);
+            }
+        }
+        None
+    }
+
+    /// Classify a last line that has no trailing newline
+    pub fn finish(&mut self) -> Option<LineKind> {
+        if !self.started {
+            return None;
+        }
+        Some(self.complete_line())
+    }
+
+    fn complete_line(&mut self) -> LineKind {
+        self.classify_part(0);
+        let kind = if self.blank {
+            LineKind::Blank
+        } else if self.has_code {
+            LineKind::Code
+        } else if self.has_comment {
+            LineKind::Comment
+        } else {
+            LineKind::Blank
+        };
+
+        self.started = false;
+        self.blank = true;
+        self.has_code = false;
+        self.has_comment = false;
+        self.in_line_comment = false;
+        self.mid_word = false;
+        kind
+    }
+
+    /// Classify the buffered line up to its last `keep` bytes and drop that part
+    fn classify_part(&mut self, keep: usize) {
+        // Keep the buffer's allocation for the rest of the line
+        let mut line = std::mem::take(&mut self.line);
+        let classified = line.len() - self.classify(&line, keep).len();
+        line.drain(..classified);
+        self.line = line;
+    }
+
+    /// Go through `line` until no more than `keep` bytes are left, returning
+    /// what is left
+    fn classify<'a>(&mut self, line: &'a str, keep: usize) -> &'a str {
+        let mut rest = line;
+
+        while rest.len() > keep {
+            if self.comment_depth > 0 {
+                self.has_comment = true;
+                rest = self.skip_comment(rest, keep);
+            } else if let Some(quote) = self.open_quote {
+                self.has_code = true;
+                rest = self.skip_string(rest, quote, keep);
+            } else if let Some(hashes) = self.open_raw_string {
+                self.has_code = true;
+                rest = self.skip_raw_string(rest, hashes, keep);
+            } else {
+                let trimmed = rest.trim_start();
+                let continues_word = self.mid_word && trimmed.len() == rest.len();
+                self.mid_word = false;
+                if trimmed.len() <= keep {
+                    // Whatever starts here may not have fully arrived yet
+                    return trimmed;
+                }
+                if self.language.line_comments.iter().any(|marker| trimmed.starts_with(marker)) {
+                    self.has_comment = true;
+                    self.in_line_comment = true;
+                    return "";
+                }
+                if let Some(&block) = self.language.block_comments.iter().find(|(start, _)| trimmed.starts_with(start)) {
+                    self.has_comment = true;
+                    self.comment_depth = 1;
+                    self.block = block;
+                    rest = &trimmed[block.0.len()..];
+                    continue;
+                }
+                self.has_code = true;
+                if let Some(&quote) = self.language.quotes.iter().find(|quote| trimmed.starts_with(*quote)) {
+                    self.open_quote = Some(quote);
+                    rest = &trimmed[quote.len()..];
+                } else if self.language.char_literals && trimmed.starts_with('\'') {
+                    rest = &trimmed[char_literal_len(trimmed).unwrap_or(1)..];
+                } else if trimmed.starts_with(is_identifier_char) {
+                    // Whole words, so that an `r` ending an identifier is no raw string prefix
+                    let end = trimmed.find(|c| !is_identifier_char(c)).unwrap_or(trimmed.len());
+                    rest = &trimmed[end..];
+                    let raw_prefix = !continues_word && matches!(&trimmed[..end], "r" | "br" | "cr");
+                    if let Some(hashes) = raw_string_hashes(rest).filter(|_| raw_prefix && self.language.raw_strings) {
+                        self.open_raw_string = Some(hashes);
+                        rest = &rest[hashes + 1..];
+                    }
+                    self.mid_word = rest.is_empty();
+                } else {
+                    let next = trimmed.chars().next().map_or(0, char::len_utf8);
+                    rest = &trimmed[next..];
+                }
+            }
+        }
+        rest
+    }
+
+    /// Skip to the end of the current block comment, tracking nesting
+    fn skip_comment<'a>(&mut self, mut rest: &'a str, keep: usize) -> &'a str {
+        let (start, end) = self.block;
+
+        while let Some(c) = rest.chars().next().filter(|_| rest.len() > keep) {
+            if let Some(after) = rest.strip_prefix(end) {
+                self.comment_depth -= 1;
+                rest = after;
+                if self.comment_depth == 0 {
+                    return rest;
+                }
+            } else if let Some(after) = rest.strip_prefix(start).filter(|_| self.language.nested_comments) {
+                self.comment_depth += 1;
+                rest = after;
+            } else {
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        rest
+    }
+
+    /// Skip to the closing quote, honouring backslash escapes
+    fn skip_string<'a>(&mut self, mut rest: &'a str, quote: &'static str, keep: usize) -> &'a str {
+        while let Some(c) = rest.chars().next().filter(|_| rest.len() > keep) {
+            if let Some(after) = rest.strip_prefix(quote) {
+                self.open_quote = None;
+                return after;
+            }
+            rest = &rest[c.len_utf8()..];
+            if c == '\\' {
+                if let Some(escaped) = rest.chars().next() {
+                    rest = &rest[escaped.len_utf8()..];
+                }
+            }
+        }
+        rest
+    }
+
+    /// Skip to the closing quote followed by `hashes` `#`s; there are no escapes
+    fn skip_raw_string<'a>(&mut self, mut rest: &'a str, hashes: usize, keep: usize) -> &'a str {
+        while let Some(c) = rest.chars().next().filter(|_| rest.len() > keep) {
+            rest = &rest[c.len_utf8()..];
+            if c == '"' && rest.len() - rest.trim_start_matches('#').len() >= hashes {
+                self.open_raw_string = None;
+                return &rest[hashes..];
+            }
+        }
+        rest
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Length of a character literal such as `'x'` or `'\''` at the start of
+/// `text`, or `None` for a lifetime like `'a`
+fn char_literal_len(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().skip(1);
+    let (_, first) = chars.next()?;
+    if first == '\\' {
+        chars.next()?;
+        // Escapes like \u{1F600} run on to the closing quote
+        chars.take(10).find(|&(_, c)| c == '\'').map(|(i, _)| i + 1)
+    } else {
+        chars.next().filter(|&(_, c)| c == '\'').map(|(i, _)| i + 1)
+    }
+}
+
+/// Number of `#`s of a raw string whose prefix was just passed, if `text`
+/// opens one
+fn raw_string_hashes(text: &str) -> Option<usize> {
+    let hashes = text.len() - text.trim_start_matches('#').len();
+    text[hashes..].starts_with('"').then_some(hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LineKind::{Blank, Code, Comment};
+
+    fn classify(extension: &str, source: &str) -> Vec<LineKind> {
+        let mut classifier = LineClassifier::new(Language::from_extension(extension).unwrap());
+        let mut kinds: Vec<_> = source.chars().filter_map(|c| classifier.push(c)).collect();
+        kinds.extend(classifier.finish());
+        kinds
+    }
+
+    #[test]
+    fn test_rust_comments_and_strings() {
+        let source = "\
+// line comment
+fn main() { // trailing comment is still code
+
+    /* block
+       comment */ let x = 1;
+    /* nested /* inner */ still comment */
+    let s = \"not // a comment
+    /* nor this */\";
+}";
+        assert_eq!(classify("rs", source), [Comment, Code, Blank, Comment, Code, Comment, Code, Code, Code]);
+    }
+
+    #[test]
+    fn test_python_hash_and_triple_quotes() {
+        let source = "# comment\nx = '#not a comment'\n\"\"\"\n# inside a docstring\n\"\"\"\n  \t\ny = 2  # trailing\n";
+        assert_eq!(classify("py", source), [Comment, Code, Code, Code, Code, Blank, Code]);
+    }
+
+    #[test]
+    fn test_escaped_quotes_do_not_end_strings() {
+        assert_eq!(classify("c", "char *s = \"a \\\" /* b\";\n/* c */\n"), [Code, Comment]);
+    }
+
+    #[test]
+    fn test_blank_lines_inside_block_comments() {
+        assert_eq!(classify("c", "/*\n\n*/\n"), [Comment, Blank, Comment]);
+    }
+
+    #[test]
+    fn test_long_lines_are_classified_in_parts() {
+        // Move every marker and literal across the point where the line is cut
+        for offset in 0..LOOKAHEAD * 2 {
+            let source = "x".repeat(MAX_LINE - LOOKAHEAD + offset)
+                + " \"a // \\\" b\" /* c */ r#\"d \" e\"# '\"' // \"\n// f\n/* g\n*/\nlet s = \"/*\";\n// h\n";
+            assert_eq!(classify("rs", &source), [Code, Comment, Comment, Comment, Code, Comment], "offset {}", offset);
+        }
+
+        let source = format!("/* {}\n*/ x\n\n// {}", "y".repeat(3 * MAX_LINE), "z".repeat(3 * MAX_LINE));
+        assert_eq!(classify("rs", &source), [Comment, Code, Blank, Comment]);
+        assert_eq!(classify("rs", &" ".repeat(3 * MAX_LINE)), [Blank]);
+    }
+
+    #[test]
+    fn test_line_without_end_stays_bounded() {
+        let mut classifier = LineClassifier::new(Language::from_extension("js").unwrap());
+        for _ in 0..100 {
+            for c in "var a=\"/*\";function f(){return a}/* x */".repeat(400).chars() {
+                assert_eq!(classifier.push(c), None);
+            }
+            assert!(classifier.line.len() <= MAX_LINE);
+        }
+
+        assert_eq!(classifier.finish(), Some(Code));
+    }
+
+    #[test]
+    fn test_rust_char_literals_are_not_strings() {
+        let source = "let q = '\"';\n// one\n// two\nlet s = \"x\";\n// three\n";
+        assert_eq!(classify("rs", source), [Code, Comment, Comment, Code, Comment]);
+
+        let source = "let c = '\\''; let d = '\\\\'; let u = '\\u{1F600}';\n// one\n";
+        assert_eq!(classify("rs", source), [Code, Comment]);
+
+        // Lifetimes and labels are left alone
+        let source = "fn f<'a>(s: &'a str) -> &'a str { s } // \"\n'outer: loop {}\n// one\n";
+        assert_eq!(classify("rs", source), [Code, Code, Comment]);
+    }
+
+    #[test]
+    fn test_rust_raw_strings() {
+        let source = "let r = r#\"a \" b\"#;\n// one\n// two\n";
+        assert_eq!(classify("rs", source), [Code, Comment, Comment]);
+
+        // No escapes, and only the matching number of `#`s ends the string
+        let source = "let r = br##\"\\\n\"# // not yet\n\"##; let p = r\"C:\\\";\n// one\n";
+        assert_eq!(classify("rs", source), [Code, Code, Code, Comment]);
+
+        // An `r` ending an identifier starts no raw string
+        assert_eq!(classify("rs", "let s = bar\"x\";\n// one\n"), [Code, Comment]);
+    }
+}
@@ -1,6 +1,14 @@
-use crate::ArgAction;
-use clap::Parser;
+use std::path::PathBuf;
 
+use clap::builder::{OsStringValueParser, PossibleValuesParser, TypedValueParser};
+use clap::{ArgAction, Parser, ValueEnum};
+use ccwc::{Encoding, LineEnding};
+use globset::Glob;
+use regex::Regex;
+
+use crate::output::{Format, TotalMode};
+
+/// A simple implementation of the wc command
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
@@ -12,6 +20,20 @@ pub struct Args {
     #[arg(short = 'l', long = "lines", action = ArgAction::SetTrue)]
     pub lines: bool,
 
+    /// Count line endings exactly, so a last line without one is not counted, like POSIX wc (the default)
+    #[arg(long = "posix", action = ArgAction::SetTrue, overrides_with = "logical_lines")]
+    pub posix: bool,
+
+    /// Also count a last line that has no line ending
+    #[arg(long = "logical-lines", action = ArgAction::SetTrue, overrides_with = "posix")]
+    pub logical_lines: bool,
+
+    /// What ends a line: lf, cr (classic Mac OS) or any of \n, \r\n and a lone \r
+    #[arg(long = "line-endings", value_name = "ENDINGS", default_value = "lf",
+          value_parser = PossibleValuesParser::new(LineEnding::NAMES)
+              .map(|name| name.parse::<LineEnding>().unwrap()))]
+    pub line_endings: LineEnding,
+
     /// Count words
     #[arg(short = 'w', long = "words", action = ArgAction::SetTrue)]
     pub words: bool,
@@ -20,7 +42,125 @@ pub struct Args {
     #[arg(short = 'm', long = "chars", action = ArgAction::SetTrue)]
     pub chars: bool,
 
-    /// File to process, if omitted reads from stdin
-    pub file: Option<String>,
+    /// Count grapheme clusters, i.e. characters as a reader sees them
+    #[arg(long = "graphemes", action = ArgAction::SetTrue)]
+    pub graphemes: bool,
+
+    /// Print the display width of the longest line
+    #[arg(short = 'L', long = "max-line-length", action = ArgAction::SetTrue)]
+    pub max_line_length: bool,
+
+    /// Also split lines of recognised languages into blank, comment and code lines
+    #[arg(long = "code", action = ArgAction::SetTrue)]
+    pub code: bool,
+
+    /// Encoding used to decode characters, words and line widths
+    #[arg(long = "encoding", value_name = "ENCODING", default_value = "utf-8",
+          value_parser = PossibleValuesParser::new(Encoding::NAMES)
+              .map(|name| name.parse::<Encoding>().unwrap()))]
+    pub encoding: Encoding,
+
+    /// How words are told apart
+    #[arg(long = "word-mode", value_enum, default_value_t = WordMode::Whitespace)]
+    pub word_mode: WordMode,
+
+    /// Count every match of REGEX as a word instead; matches do not span lines
+    #[arg(long = "word-regex", value_name = "REGEX", value_parser = Regex::new, conflicts_with = "word_mode")]
+    pub word_regex: Option<Regex>,
+
+    /// Add a column counting the matches of REGEX, line by line; may be repeated
+    #[arg(long = "count-pattern", value_name = "REGEX", value_parser = Regex::new)]
+    pub count_pattern: Vec<Regex>,
+
+    /// Add a column counting the bytes with the hexadecimal value HEX, e.g. 0a or 0x00; may be repeated
+    #[arg(long = "count-byte", value_name = "HEX", value_parser = parse_byte)]
+    pub count_byte: Vec<u8>,
+
+    /// Compare two files, or one file at two revisions: print both rows, then
+    /// the difference and the change in percent for every column
+    #[arg(long = "diff", action = ArgAction::SetTrue,
+          conflicts_with_all = ["recursive", "by_extension", "follow", "files0_from"])]
+    pub diff: bool,
+
+    /// Count files as they were at git revision REV; with --diff, compare REV
+    /// with the working tree, or with a second --git-rev
+    #[arg(long = "git-rev", value_name = "REV", conflicts_with_all = ["recursive", "follow", "files0_from"])]
+    pub git_rev: Vec<String>,
+
+    /// Count the decompressed content of gzip and HUFF compressed inputs
+    #[arg(short = 'z', long = "decompress", action = ArgAction::SetTrue, conflicts_with = "follow")]
+    pub decompress: bool,
+
+    /// Count the files below directory operands, the current directory if none are given
+    #[arg(short = 'r', long = "recursive", action = ArgAction::SetTrue)]
+    pub recursive: bool,
+
+    /// With -r, only count files matching GLOB (matched against the name or relative path)
+    #[arg(long = "include", value_name = "GLOB", value_parser = Glob::new, requires = "recursive")]
+    pub include: Vec<Glob>,
+
+    /// With -r, skip files and directories matching GLOB
+    #[arg(long = "exclude", value_name = "GLOB", value_parser = Glob::new, requires = "recursive")]
+    pub exclude: Vec<Glob>,
+
+    /// With -r, also count hidden files and files listed in .gitignore and .ignore
+    #[arg(long = "no-ignore", action = ArgAction::SetTrue, requires = "recursive")]
+    pub no_ignore: bool,
+
+    /// Print a summary per file extension instead of a row per file
+    #[arg(long = "by-extension", action = ArgAction::SetTrue)]
+    pub by_extension: bool,
+
+    /// Keep the file open and print updated counts whenever it grows
+    #[arg(long = "follow", action = ArgAction::SetTrue, conflicts_with_all = ["recursive", "by_extension"])]
+    pub follow: bool,
+
+    /// With --follow, seconds to wait between checks for new data
+    #[arg(long = "interval", value_name = "SECONDS", default_value_t = 1.0, requires = "follow")]
+    pub interval: f64,
+
+    /// Read NUL-terminated file names from F, or from stdin when F is -
+    #[arg(long = "files0-from", value_name = "F", conflicts_with = "files")]
+    pub files0_from: Option<String>,
+
+    /// Output format
+    #[arg(long = "format", value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// Follow each row with line length statistics and a histogram
+    #[arg(long = "stats", action = ArgAction::SetTrue)]
+    pub stats: bool,
+
+    /// When to print the total row
+    #[arg(long = "total", value_name = "WHEN", value_enum, default_value_t = TotalMode::Auto)]
+    pub total: TotalMode,
+
+    /// Memory-map large files instead of reading them; a file truncated while
+    /// being counted then kills ccwc with SIGBUS
+    #[arg(long = "mmap", action = ArgAction::SetTrue)]
+    pub mmap: bool,
+
+    /// Number of files to count concurrently
+    #[arg(short = 'j', long = "jobs", value_name = "N", default_value_t = 1,
+          value_parser = clap::value_parser!(u16).range(1..))]
+    pub jobs: u16,
+
+    /// Files to process, if omitted reads from stdin
+    // Clap's path parser refuses empty names, which are reported per operand instead
+    #[arg(value_parser = OsStringValueParser::new().map(PathBuf::from))]
+    pub files: Vec<PathBuf>,
+}
+
+/// Built-in word definitions selectable with `--word-mode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum WordMode {
+    /// Runs of non-whitespace characters, like POSIX wc
+    Whitespace,
+    /// Unicode word boundaries (UAX #29), counting each CJK ideograph and skipping punctuation
+    Unicode,
 }
 
+fn parse_byte(hex: &str) -> Result<u8, String> {
+    let digits = hex.strip_prefix("0x").or_else(|| hex.strip_prefix("0X")).unwrap_or(hex);
+    u8::from_str_radix(digits, 16).map_err(|_| format!("expected a byte in hexadecimal, like 0a: {}", hex))
+}
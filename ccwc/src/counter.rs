@@ -0,0 +1,733 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use memmap2::Mmap;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+use crate::code::{LineClassifier, LineKind};
+use crate::decompress::decompress;
+use crate::encoding::{Decoder, Encoding};
+use crate::fast::count_byte;
+use crate::language::Language;
+use crate::lines::LineEnding;
+use crate::stats::LineLengths;
+use crate::words::{RegexWords, WordBoundaries, WordSplitter};
+use regex::Regex;
+
+/// Size of the buffer used when streaming input through the counter
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Regular files at least this large are memory-mapped rather than read
+/// when `CountOptions::mmap` is set; below it setting up the map costs more
+/// than skipping the copies saves (see `benches/count.rs`)
+pub const MMAP_THRESHOLD: u64 = 1024 * 1024;
+
+/// Counts gathered for a single input, summed up for the total line
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Counts {
+    pub lines: usize,
+    pub words: usize,
+    pub bytes: usize,
+    pub chars: usize,
+    /// Display width of the longest line, not including its line terminator
+    pub max_line_length: usize,
+    /// Extended grapheme clusters, i.e. characters as a reader perceives them
+    pub graphemes: usize,
+    /// Lines of a recognised language that are empty or only whitespace
+    pub blank_lines: usize,
+    /// Lines of a recognised language holding nothing but comments
+    pub comment_lines: usize,
+    /// Lines of a recognised language holding any code
+    pub code_lines: usize,
+    /// Display width of every line, for line length statistics
+    pub line_lengths: LineLengths,
+    /// Matches of each of `CountOptions::patterns`
+    pub pattern_counts: Vec<usize>,
+    /// Occurrences of each of `CountOptions::byte_values`
+    pub byte_counts: Vec<usize>,
+}
+
+impl Counts {
+    /// Fold another set of counts into this one, as done for the total line
+    pub fn add(&mut self, other: &Counts) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.bytes += other.bytes;
+        self.chars += other.chars;
+        self.graphemes += other.graphemes;
+        self.blank_lines += other.blank_lines;
+        self.comment_lines += other.comment_lines;
+        self.code_lines += other.code_lines;
+        self.max_line_length = self.max_line_length.max(other.max_line_length);
+        self.line_lengths.add(&other.line_lengths);
+        add_each(&mut self.pattern_counts, &other.pattern_counts);
+        add_each(&mut self.byte_counts, &other.byte_counts);
+    }
+}
+
+fn add_each(totals: &mut Vec<usize>, other: &[usize]) {
+    if totals.len() < other.len() {
+        totals.resize(other.len(), 0);
+    }
+    totals.iter_mut().zip(other).for_each(|(total, count)| *total += count);
+}
+
+/// Which counts a `Counter` has to produce, and how to decode the input.
+///
+/// Counts that are not requested are left at zero, which lets the counter
+/// skip decoding when only lines and bytes are needed. The byte count is
+/// always available since it costs nothing.
+#[derive(Debug, Clone)]
+pub struct CountOptions {
+    pub lines: bool,
+    /// Also count a last line that has no line ending, rather than counting
+    /// line endings exactly like POSIX wc
+    pub logical_lines: bool,
+    /// What ends a line for the line count
+    pub line_ending: LineEnding,
+    pub words: bool,
+    pub chars: bool,
+    pub max_line_length: bool,
+    pub graphemes: bool,
+    /// Split lines into blank, comment and code lines
+    pub code: bool,
+    /// Record the length of every line
+    pub line_stats: bool,
+    /// Language used for `code`; `count_path` detects it from the extension when unset
+    pub language: Option<&'static Language>,
+    pub encoding: Encoding,
+    /// What counts as a word
+    pub word_boundaries: WordBoundaries,
+    /// Count the content of gzip or HUFF compressed input instead of its raw bytes
+    pub decompress: bool,
+    /// Regular expressions whose non-empty matches are counted, line by line;
+    /// overlong lines are matched in pieces like `WordBoundaries::Regex` words
+    pub patterns: Vec<Regex>,
+    /// Byte values whose occurrences are counted in the raw input
+    pub byte_values: Vec<u8>,
+    /// Let `count_path` memory-map large regular files instead of reading them.
+    /// Off by default: should another process truncate a mapped file while it
+    /// is counted, as logrotate's copytruncate does, the process gets SIGBUS.
+    pub mmap: bool,
+}
+
+impl Default for CountOptions {
+    /// All of wc's counts are produced by default, decoding the input as
+    /// UTF-8; graphemes and code lines are more expensive and have to be
+    /// opted into
+    fn default() -> Self {
+        CountOptions {
+            lines: true,
+            logical_lines: false,
+            line_ending: LineEnding::Lf,
+            words: true,
+            chars: true,
+            max_line_length: true,
+            graphemes: false,
+            code: false,
+            line_stats: false,
+            language: None,
+            encoding: Encoding::Utf8,
+            word_boundaries: WordBoundaries::default(),
+            decompress: false,
+            patterns: Vec::new(),
+            byte_values: Vec::new(),
+            mmap: false,
+        }
+    }
+}
+
+impl CountOptions {
+    /// Only the byte count was asked for, and it is the size of the input
+    pub fn bytes_only(&self) -> bool {
+        !self.lines && !self.decompress && self.byte_values.is_empty() && !self.needs_decoding()
+    }
+
+    /// For `code`, pick the language by the extension of `path` unless one was given
+    pub fn with_language_from<P: AsRef<Path>>(mut self, path: P) -> Self {
+        if self.code && self.language.is_none() {
+            self.language = Language::from_path(path);
+        }
+        self
+    }
+
+    fn needs_decoding(&self) -> bool {
+        self.words || self.chars || self.max_line_length || self.graphemes || self.line_stats || !self.patterns.is_empty() || self.classifies_lines() || self.lines_from_chars()
+    }
+
+    fn classifies_lines(&self) -> bool {
+        self.code && self.language.is_some()
+    }
+
+    /// Newline bytes can only be counted directly in ASCII compatible encodings
+    fn lines_from_chars(&self) -> bool {
+        self.lines && !self.encoding.is_ascii_compatible()
+    }
+}
+
+/// Columns between tab stops when measuring line width
+const TAB_WIDTH: usize = 8;
+
+/// Incremental counter fed with arbitrary chunks of input.
+///
+/// Words and multi-byte characters may straddle chunk boundaries, so the
+/// counter carries the unfinished state over to the next call of `update`.
+#[derive(Debug, Clone)]
+pub struct Counter {
+    options: CountOptions,
+    decoder: Decoder,
+    text: TextState,
+    bytes: usize,
+    byte_lines: usize,
+    last_byte: Option<u8>,
+}
+
+/// Counts that depend on decoded characters rather than raw bytes
+#[derive(Debug, Default, Clone)]
+struct TextState {
+    counts: Counts,
+    // Only present when words are counted
+    words: Option<Box<dyn WordSplitter>>,
+    // Matches of `patterns` are found the same way as `--word-regex` words
+    patterns: Vec<RegexWords>,
+    line_ending: LineEnding,
+    line_length: usize,
+    // Width of the whole line for `line_stats`, which unlike `-L` does not
+    // start over at a carriage return
+    line_width: Option<usize>,
+    last_char: Option<char>,
+    segment_graphemes: bool,
+    // Decoded text not yet split into grapheme clusters
+    cluster_text: String,
+    classifier: Option<LineClassifier>,
+}
+
+impl Counter {
+    pub fn new() -> Self {
+        Self::with_options(CountOptions::default())
+    }
+
+    pub fn with_options(options: CountOptions) -> Self {
+        let words = options.words.then(|| options.word_boundaries.splitter());
+        Self::with_word_splitter(options, words)
+    }
+
+    /// Like `with_options`, but splitting words with a custom splitter
+    /// instead of one of the built-in `WordBoundaries`
+    pub fn with_word_splitter(options: CountOptions, words: Option<Box<dyn WordSplitter>>) -> Self {
+        let counts = Counts {
+            pattern_counts: vec![0; options.patterns.len()],
+            byte_counts: vec![0; options.byte_values.len()],
+            ..Counts::default()
+        };
+        let text = TextState {
+            counts,
+            words,
+            patterns: options.patterns.iter().cloned().map(RegexWords::new).collect(),
+            line_ending: options.line_ending,
+            segment_graphemes: options.graphemes,
+            line_width: options.line_stats.then_some(0),
+            classifier: options.language.filter(|_| options.code).map(LineClassifier::new),
+            ..TextState::default()
+        };
+        Counter { decoder: Decoder::new(options.encoding), options, text, bytes: 0, byte_lines: 0, last_byte: None }
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) {
+        if chunk.is_empty() {
+            return;
+        }
+
+        if self.options.lines && !self.options.lines_from_chars() {
+            self.byte_lines += self.options.line_ending.count(self.last_byte, chunk);
+        }
+        self.bytes += chunk.len();
+        self.last_byte = chunk.last().copied();
+
+        for (count, &byte) in self.text.counts.byte_counts.iter_mut().zip(&self.options.byte_values) {
+            *count += count_byte(chunk, byte);
+        }
+
+        if self.options.needs_decoding() {
+            self.decoder.decode(chunk, |c| self.text.char(c));
+            self.text.segment(false);
+            if let Some(words) = self.text.words.as_mut() {
+                self.text.counts.words += words.flush();
+            }
+            for (count, pattern) in self.text.counts.pattern_counts.iter_mut().zip(&mut self.text.patterns) {
+                *count += pattern.flush();
+            }
+        }
+    }
+
+    /// Counts for the input seen so far, as if it ended here, without
+    /// disturbing the state needed to carry on with further chunks
+    pub fn counts(&self) -> Counts {
+        self.clone().finish()
+    }
+
+    pub fn finish(mut self) -> Counts {
+        // A sequence truncated at the very end is malformed
+        self.decoder.finish(|c| self.text.char(c));
+        self.text.segment(true);
+        if let Some(words) = self.text.words.as_mut() {
+            self.text.counts.words += words.finish();
+        }
+        for (count, pattern) in self.text.counts.pattern_counts.iter_mut().zip(&mut self.text.patterns) {
+            *count += pattern.finish();
+        }
+        if let Some(kind) = self.text.classifier.as_mut().and_then(LineClassifier::finish) {
+            self.text.count_line(kind);
+        }
+        if let Some(width) = self.text.line_width.filter(|_| self.bytes > 0 && self.text.last_char != Some('\n')) {
+            self.text.counts.line_lengths.record(width);
+        }
+
+        let mut counts = self.text.counts;
+        counts.bytes = self.bytes;
+
+        // Logical lines include a last line without a line ending
+        let ending = self.options.line_ending;
+        if self.options.lines_from_chars() {
+            let unterminated = matches!(self.text.last_char, Some(c) if !ending.is_terminator(c));
+            counts.lines += (self.options.logical_lines && unterminated) as usize;
+        } else if self.options.lines {
+            let unterminated = matches!(self.last_byte, Some(b) if !ending.is_terminator(b as char));
+            counts.lines = self.byte_lines + (self.options.logical_lines && unterminated) as usize;
+        } else {
+            counts.lines = 0;
+        }
+
+        counts
+    }
+}
+
+impl Default for Counter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextState {
+    /// Account for one decoded character, or `None` for a malformed sequence,
+    /// which is not a character but is still handed to the word splitter
+    fn char(&mut self, c: Option<char>) {
+        let previous = std::mem::replace(&mut self.last_char, c);
+        if self.segment_graphemes {
+            // Shown as a replacement character, which is a cluster of its own
+            self.cluster_text.push(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+        if let Some(words) = self.words.as_mut() {
+            self.counts.words += words.push(c);
+        }
+        for pattern in &mut self.patterns {
+            pattern.push(c);
+        }
+
+        let Some(c) = c else {
+            return;
+        };
+
+        self.counts.chars += 1;
+
+        // Display width as wc -L sees it: tabs advance to the next tab stop,
+        // wide characters take two columns and control characters none
+        match c {
+            '\n' | '\r' | '\x0c' => self.line_length = 0,
+            '\t' => self.line_length += TAB_WIDTH - self.line_length % TAB_WIDTH,
+            _ => self.line_length += c.width().unwrap_or(0),
+        }
+        self.counts.max_line_length = self.counts.max_line_length.max(self.line_length);
+
+        if let Some(width) = self.line_width.as_mut() {
+            match c {
+                '\n' => {
+                    self.counts.line_lengths.record(*width);
+                    *width = 0;
+                }
+                '\t' => *width += TAB_WIDTH - *width % TAB_WIDTH,
+                _ => *width += c.width().unwrap_or(0),
+            }
+        }
+
+        if self.line_ending.ends_line(previous, c) {
+            self.counts.lines += 1;
+        }
+
+        if let Some(kind) = self.classifier.as_mut().and_then(|classifier| classifier.push(c)) {
+            self.count_line(kind);
+        }
+    }
+
+    /// Count the grapheme clusters in the text decoded so far.
+    ///
+    /// The last cluster may still grow with the next chunk (a combining mark
+    /// or the rest of an emoji sequence), so it is kept back until the end.
+    fn segment(&mut self, last: bool) {
+        if self.cluster_text.is_empty() {
+            return;
+        }
+
+        let mut clusters = self.cluster_text.grapheme_indices(true);
+        let unfinished = if last { None } else { clusters.next_back().map(|(start, _)| start) };
+        self.counts.graphemes += clusters.count();
+
+        match unfinished {
+            Some(start) => {
+                self.cluster_text.drain(..start);
+            }
+            None => self.cluster_text.clear(),
+        }
+    }
+
+    fn count_line(&mut self, kind: LineKind) {
+        match kind {
+            LineKind::Blank => self.counts.blank_lines += 1,
+            LineKind::Comment => self.counts.comment_lines += 1,
+            LineKind::Code => self.counts.code_lines += 1,
+        }
+    }
+}
+
+/// Count a reader to the end, reading at most `CHUNK_SIZE` bytes at a time
+pub fn count<R: Read>(reader: R) -> io::Result<Counts> {
+    count_with(reader, CountOptions::default())
+}
+
+/// Like `count`, but only producing the counts selected in `options`
+pub fn count_with<R: Read>(mut reader: R, options: CountOptions) -> io::Result<Counts> {
+    if options.decompress {
+        return count_with(decompress(reader)?, CountOptions { decompress: false, ..options });
+    }
+
+    let mut counter = Counter::with_options(options);
+    let mut buffer = vec![0; CHUNK_SIZE];
+
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => counter.update(&buffer[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(counter.finish())
+}
+
+/// Count a file by path, taking the size of regular files from their
+/// metadata instead of reading them when only bytes are requested
+pub fn count_path<P: AsRef<Path>>(path: P, options: CountOptions) -> io::Result<Counts> {
+    let options = options.with_language_from(&path);
+    let file = File::open(path)?;
+    let metadata = file.metadata()?;
+
+    // Files in /proc and the like claim to be empty, so those still get read
+    if options.bytes_only() && metadata.is_file() && metadata.len() > 0 {
+        return Ok(Counts { bytes: metadata.len() as usize, ..Counts::default() });
+    }
+
+    // Pipes, FIFOs and devices cannot be mapped and are read as they come
+    if options.mmap && metadata.is_file() && metadata.len() >= MMAP_THRESHOLD {
+        // SAFETY: the map is only read. Should another process truncate the
+        // file while it is being counted, touching the lost pages raises
+        // SIGBUS, which is why callers have to ask for mapping.
+        if let Ok(map) = unsafe { Mmap::map(&file) } {
+            #[cfg(unix)]
+            let _ = map.advise(memmap2::Advice::Sequential);
+            return count_slice(&map, options);
+        }
+        // Some file systems do not support mapping, so fall back to reading
+    }
+
+    count_with(file, options)
+}
+
+/// Count input that is already in memory, such as a memory-mapped file.
+///
+/// The counter is still fed `CHUNK_SIZE` bytes at a time, which keeps the
+/// text it holds back between chunks as small as when reading.
+pub fn count_slice(bytes: &[u8], options: CountOptions) -> io::Result<Counts> {
+    if options.decompress {
+        return count_with(bytes, options);
+    }
+
+    let mut counter = Counter::with_options(options);
+    bytes.chunks(CHUNK_SIZE).for_each(|chunk| counter.update(chunk));
+    Ok(counter.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unicode_width::UnicodeWidthStr;
+
+    fn reference(content: &[u8]) -> Counts {
+        let text = String::from_utf8_lossy(content);
+        Counts {
+            lines: content.iter().filter(|&&b| b == b'\n').count(),
+            words: text.split_whitespace().count(),
+            bytes: content.len(),
+            chars: text.chars().count(),
+            max_line_length: text.lines().map(|line| line.width()).max().unwrap_or(0),
+            ..Counts::default()
+        }
+    }
+
+    fn count_in_chunks(content: &[u8], chunk_size: usize) -> Counts {
+        let mut counter = Counter::new();
+        content.chunks(chunk_size).for_each(|chunk| counter.update(chunk));
+        counter.finish()
+    }
+
+    #[test]
+    fn test_matches_whole_input_for_every_chunk_size() {
+        let content = "Hello, 世界!\nThis is a 测试 file.\nThird 行 🦀\nno newline".as_bytes();
+
+        for chunk_size in 1..=content.len() {
+            assert_eq!(count_in_chunks(content, chunk_size), reference(content), "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn test_word_split_across_chunks() {
+        let mut counter = Counter::new();
+        counter.update(b"hel");
+        counter.update(b"lo wor");
+        counter.update(b"ld\n");
+
+        assert_eq!(counter.finish().words, 2);
+    }
+
+    #[test]
+    fn test_invalid_utf8_is_not_counted_as_chars() {
+        let content = b"ab\xff\xfe cd \xe4\xb8 \xf0\x9f\xa6";
+        let expected = Counts { lines: 0, words: 4, bytes: content.len(), chars: 7, max_line_length: 7, ..Counts::default() };
+
+        for chunk_size in 1..=content.len() {
+            assert_eq!(count_in_chunks(content, chunk_size), expected, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn test_utf16_counts_decoded_lines_and_chars() {
+        let text = "Hello, 世界\n\u{10a}bc\n";
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+
+        for encoding in [Encoding::Utf16Le, Encoding::Auto] {
+            let options = CountOptions { encoding, ..CountOptions::default() };
+            let counts = count_with(&bytes[..], options).unwrap();
+
+            assert_eq!(counts.lines, 2, "{}", encoding);
+            assert_eq!(counts.words, 3, "{}", encoding);
+            assert_eq!(counts.chars, text.chars().count() + 1, "{}", encoding);
+            assert_eq!(counts.bytes, bytes.len(), "{}", encoding);
+        }
+    }
+
+    #[test]
+    fn test_graphemes_across_every_chunk_size() {
+        // Family emoji joined with ZWJ, a flag, and e with a combining acute accent
+        let content = "👨\u{200d}👩\u{200d}👧 🇯🇵 e\u{301}!\n".as_bytes();
+        let options = CountOptions { graphemes: true, ..CountOptions::default() };
+
+        for chunk_size in 1..=content.len() {
+            let mut counter = Counter::with_options(options.clone());
+            content.chunks(chunk_size).for_each(|chunk| counter.update(chunk));
+            let counts = counter.finish();
+
+            assert_eq!(counts.graphemes, 7, "chunk size {}", chunk_size);
+            assert_eq!(counts.chars, 13, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn test_code_lines_for_recognised_languages() {
+        let mut file = tempfile::Builder::new().suffix(".rs").tempfile().unwrap();
+        io::Write::write_all(&mut file, b"// comment\n\nfn main() {\n    /* a\n    b */\n}").unwrap();
+        let options = CountOptions { code: true, ..CountOptions::default() };
+
+        let counts = count_path(file.path(), options.clone()).unwrap();
+
+        assert_eq!((counts.blank_lines, counts.comment_lines, counts.code_lines), (1, 3, 2));
+        // The closing brace has no newline, so wc -l would not count it
+        assert_eq!(counts.lines, 5);
+
+        // Without a known language there is nothing to split
+        let counts = count_with(&b"// comment\n"[..], options).unwrap();
+        assert_eq!((counts.blank_lines, counts.comment_lines, counts.code_lines), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_word_boundaries() {
+        let content = "let x1 = 你好(y);\n".as_bytes();
+        let words = |word_boundaries| count_with(content, CountOptions { word_boundaries, ..CountOptions::default() }).unwrap().words;
+
+        assert_eq!(words(WordBoundaries::Whitespace), 4);
+        assert_eq!(words(WordBoundaries::Unicode), 5);
+        assert_eq!(words(WordBoundaries::Regex(regex::Regex::new(r"[a-z]\w*").unwrap())), 3);
+    }
+
+    #[test]
+    fn test_line_lengths() {
+        let options = CountOptions { line_stats: true, ..CountOptions::default() };
+        let counts = count_with("ab\r\n\tx\n\n世界".as_bytes(), options).unwrap();
+
+        let mut expected = LineLengths::new();
+        [2, 9, 0, 4].into_iter().for_each(|length| expected.record(length));
+        assert_eq!(counts.line_lengths, expected);
+        assert_eq!(count_with(&b""[..], CountOptions { line_stats: true, ..CountOptions::default() }).unwrap().line_lengths.lines(), 0);
+    }
+
+    #[test]
+    fn test_patterns_and_byte_values() {
+        let options = CountOptions {
+            patterns: ["ERROR", r"\d+", "^ERROR", r"\d+$", "^ok"].into_iter().map(|pattern| Regex::new(pattern).unwrap()).collect(),
+            byte_values: vec![b',', 0xff],
+            ..CountOptions::default()
+        };
+        let content = b"ERROR 1, ERROR 22\nok 333,\xff\n";
+
+        for chunk_size in 1..=content.len() {
+            let mut counter = Counter::with_options(options.clone());
+            content.chunks(chunk_size).for_each(|chunk| counter.update(chunk));
+            let counts = counter.finish();
+
+            // Anchors match at the start and end of each line, wherever the chunks end
+            assert_eq!(counts.pattern_counts, [2, 3, 1, 1, 1], "chunk size {}", chunk_size);
+            assert_eq!(counts.byte_counts, [2, 1], "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn test_patterns_on_a_log_without_newlines() {
+        let options = CountOptions { patterns: vec![Regex::new("ERROR").unwrap()], ..CountOptions::default() };
+        let content = "ok ERROR ok ".repeat(100_000);
+
+        let counts = count_with(content.as_bytes(), options).unwrap();
+        assert_eq!(counts.pattern_counts, [100_000]);
+    }
+
+    #[test]
+    fn test_mapped_and_read_files_agree() {
+        let line = "Hello, wörld\t世界 👨\u{200d}👩\u{200d}👧\n".repeat(3);
+        let content = line.repeat(MMAP_THRESHOLD as usize / line.len() + 100);
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        io::Write::write_all(&mut file, content.as_bytes()).unwrap();
+
+        let options = CountOptions { graphemes: true, line_stats: true, ..CountOptions::default() };
+        let mapped = count_path(file.path(), CountOptions { mmap: true, ..options.clone() }).unwrap();
+        let read = count_path(file.path(), options).unwrap();
+
+        assert_eq!(mapped, read);
+        assert_eq!(mapped.bytes, content.len());
+        let expected = reference(content.as_bytes());
+        assert_eq!((mapped.lines, mapped.words, mapped.chars), (expected.lines, expected.words, expected.chars));
+    }
+
+    #[test]
+    fn test_latin1_counts_every_byte_as_char() {
+        let options = CountOptions { encoding: Encoding::Latin1, ..CountOptions::default() };
+
+        assert_eq!(count_with(&b"caf\xe9 cr\xe8me\n"[..], options).unwrap().chars, 11);
+    }
+
+    #[test]
+    fn test_count_reader_streams_large_input() {
+        let content = "word ".repeat(CHUNK_SIZE);
+
+        let counts = count(content.as_bytes()).unwrap();
+
+        assert_eq!(counts.words, CHUNK_SIZE);
+        assert_eq!(counts.bytes, content.len());
+        assert_eq!(counts.lines, 0);
+        assert_eq!(counts.max_line_length, content.len());
+    }
+
+    #[test]
+    fn test_total_keeps_longest_line() {
+        let mut total = count(&b"a\nabc\n"[..]).unwrap();
+        total.add(&count(&b"ab\n"[..]).unwrap());
+
+        assert_eq!(total.lines, 3);
+        assert_eq!(total.max_line_length, 3);
+    }
+
+    #[test]
+    fn test_max_line_length_uses_display_width() {
+        assert_eq!(count(&b"a\tb\n"[..]).unwrap().max_line_length, 9);
+        assert_eq!(count(&b"abcdefgh\tb\n"[..]).unwrap().max_line_length, 17);
+        assert_eq!(count("世界\nabc\n".as_bytes()).unwrap().max_line_length, 4);
+        assert_eq!(count(&b"long line\rab\n"[..]).unwrap().max_line_length, 9);
+    }
+
+    #[test]
+    fn test_lines_only_skips_decoding() {
+        let options = CountOptions { lines: true, words: false, chars: false, max_line_length: false, ..CountOptions::default() };
+
+        let counts = count_with("Hello, 世界\nmore".as_bytes(), options).unwrap();
+
+        assert_eq!(counts, Counts { lines: 1, bytes: 18, ..Counts::default() });
+    }
+
+    #[test]
+    fn test_posix_and_logical_lines() {
+        let logical = CountOptions { logical_lines: true, ..CountOptions::default() };
+        let lines_only = CountOptions { words: false, chars: false, max_line_length: false, ..logical.clone() };
+
+        for content in ["one\ntwo", "one\ntwo\n", "", "\n", "no newline"] {
+            let posix = content.matches('\n').count();
+            let expected = content.lines().count();
+            assert_eq!(count(content.as_bytes()).unwrap().lines, posix, "{:?}", content);
+            assert_eq!(count_with(content.as_bytes(), logical.clone()).unwrap().lines, expected, "{:?}", content);
+            assert_eq!(count_with(content.as_bytes(), lines_only.clone()).unwrap().lines, expected, "{:?}", content);
+        }
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let content = "unix\ndos\r\nmac\rend";
+        let bytes = content.as_bytes();
+        let mut utf16 = Vec::new();
+        utf16.extend(content.encode_utf16().flat_map(u16::to_le_bytes));
+
+        for (line_ending, posix, logical) in [(LineEnding::Lf, 2, 3), (LineEnding::Cr, 2, 3), (LineEnding::Any, 3, 4)] {
+            let options = CountOptions { line_ending, ..CountOptions::default() };
+            let logical_options = CountOptions { logical_lines: true, ..options.clone() };
+            let utf16_options = CountOptions { encoding: Encoding::Utf16Le, ..logical_options.clone() };
+
+            assert_eq!(count_with(bytes, options).unwrap().lines, posix, "{}", line_ending);
+            assert_eq!(count_with(bytes, logical_options).unwrap().lines, logical, "{}", line_ending);
+            assert_eq!(count_with(&utf16[..], utf16_options).unwrap().lines, logical, "{}", line_ending);
+        }
+    }
+
+    #[test]
+    fn test_count_path_bytes_only_uses_metadata() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        io::Write::write_all(&mut file, b"one\ntwo\n").unwrap();
+        let options = CountOptions { lines: false, words: false, chars: false, max_line_length: false, ..CountOptions::default() };
+
+        assert_eq!(count_path(file.path(), options).unwrap(), Counts { bytes: 8, ..Counts::default() });
+        assert_eq!(count_path(file.path(), CountOptions::default()).unwrap().lines, 2);
+    }
+
+    #[test]
+    fn test_counts_so_far_leave_the_counter_untouched() {
+        let mut counter = Counter::new();
+        counter.update(b"one tw");
+
+        assert_eq!(counter.counts().words, 2);
+        counter.update(b"o three\n");
+        assert_eq!(counter.counts().words, 3);
+        assert_eq!(counter.finish().lines, 1);
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert_eq!(count(&b""[..]).unwrap(), Counts::default());
+    }
+}
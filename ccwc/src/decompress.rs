@@ -0,0 +1,282 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, BufRead, BufReader, Read};
+
+use flate2::read::MultiGzDecoder;
+
+/// Compressed formats recognised by `decompress`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    /// The container written by the huffman tool: `HUFF`, a table of symbol
+    /// frequencies, the number of encoded bits and the bits themselves
+    Huff,
+}
+
+impl Compression {
+    /// Recognise a format from the first bytes of its input
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        if header.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if header.starts_with(b"HUFF") {
+            Some(Compression::Huff)
+        } else {
+            None
+        }
+    }
+}
+
+/// Wrap a reader so that it yields the decompressed content of gzip or HUFF
+/// input; anything else is passed through unchanged, like `zcat -f`
+pub fn decompress<'a, R: Read + 'a>(mut reader: R) -> io::Result<Box<dyn Read + 'a>> {
+    let mut header = [0; 4];
+    let mut len = 0;
+    while len < header.len() {
+        match reader.read(&mut header[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    // Put the sniffed bytes back in front of the rest
+    let reader = io::Cursor::new(header).take(len as u64).chain(reader);
+    Ok(match Compression::detect(&header[..len]) {
+        Some(Compression::Gzip) => Box::new(MultiGzDecoder::new(reader)),
+        Some(Compression::Huff) => Box::new(HuffDecoder::new(BufReader::new(reader))?),
+        None => Box::new(reader),
+    })
+}
+
+/// A node of the Huffman tree, either a symbol or the indices of its children
+#[derive(Debug, Clone, Copy)]
+enum Node {
+    Leaf(u8),
+    Branch(usize, usize),
+}
+
+/// Streaming decoder for the HUFF container.
+///
+/// The tree is rebuilt from the frequency table exactly as the encoder built
+/// it, breaking ties between equal frequencies by symbol and then by order of
+/// creation. The decoded symbols and the number of bits used are checked
+/// against the header at the end, which catches most corrupt data and data
+/// encoded with a different tree.
+#[derive(Debug)]
+pub struct HuffDecoder<R> {
+    reader: R,
+    nodes: Vec<Node>,
+    expected: [u64; 256],
+    decoded: [u64; 256],
+    // Symbols still to decode, and encoded bits still to read
+    remaining: u64,
+    bits_left: u64,
+    byte: u8,
+    bits_in_byte: u8,
+}
+
+impl<R: BufRead> HuffDecoder<R> {
+    /// Read the header, leaving the reader at the start of the encoded bits
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != b"HUFF" {
+            return Err(invalid_data("not HUFF data"));
+        }
+
+        let mut expected = [0; 256];
+        let mut count = [0; 2];
+        reader.read_exact(&mut count)?;
+        for _ in 0..u16::from_be_bytes(count) {
+            let mut entry = [0; 9];
+            reader.read_exact(&mut entry)?;
+            expected[entry[0] as usize] = u64::from_be_bytes(entry[1..].try_into().unwrap());
+        }
+
+        let mut bit_length = [0; 8];
+        reader.read_exact(&mut bit_length)?;
+
+        // The counts come from the file, so their sums may not fit
+        let remaining = expected.iter().try_fold(0u64, |sum, &count| sum.checked_add(count));
+        Ok(HuffDecoder {
+            reader,
+            nodes: build_tree(&expected)?,
+            expected,
+            decoded: [0; 256],
+            remaining: remaining.ok_or_else(|| invalid_data("HUFF symbol counts overflow"))?,
+            bits_left: u64::from_be_bytes(bit_length),
+            byte: 0,
+            bits_in_byte: 0,
+        })
+    }
+
+    fn next_bit(&mut self) -> io::Result<bool> {
+        if self.bits_left == 0 {
+            return Err(invalid_data("HUFF data ends early"));
+        }
+        if self.bits_in_byte == 0 {
+            let mut byte = [0];
+            self.reader.read_exact(&mut byte)?;
+            (self.byte, self.bits_in_byte) = (byte[0], 8);
+        }
+        self.bits_left -= 1;
+        self.bits_in_byte -= 1;
+        Ok(self.byte >> self.bits_in_byte & 1 == 1)
+    }
+
+    fn next_symbol(&mut self) -> io::Result<u8> {
+        // The root is the last node built; a lone symbol is encoded without bits
+        let mut node = self.nodes[self.nodes.len() - 1];
+        loop {
+            match node {
+                Node::Leaf(symbol) => return Ok(symbol),
+                Node::Branch(left, right) => {
+                    let child = if self.next_bit()? { right } else { left };
+                    node = self.nodes[child];
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Read for HuffDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut written = 0;
+        while written < buf.len() && self.remaining > 0 {
+            let symbol = self.next_symbol()?;
+            self.decoded[symbol as usize] += 1;
+            self.remaining -= 1;
+            buf[written] = symbol;
+            written += 1;
+        }
+
+        if self.remaining == 0 && (self.bits_left != 0 || self.decoded != self.expected) {
+            return Err(invalid_data("HUFF data does not match its symbol table"));
+        }
+        Ok(written)
+    }
+}
+
+/// Rebuild the Huffman tree, returning its nodes with the root last
+fn build_tree(frequencies: &[u64; 256]) -> io::Result<Vec<Node>> {
+    let mut nodes = Vec::new();
+    let mut heap = BinaryHeap::new();
+    for (symbol, &frequency) in frequencies.iter().enumerate().filter(|(_, &frequency)| frequency > 0) {
+        heap.push(Reverse((frequency, symbol, nodes.len())));
+        nodes.push(Node::Leaf(symbol as u8));
+    }
+
+    let mut order = 256;
+    while let (Some(Reverse((left_frequency, _, left))), Some(Reverse((right_frequency, _, right)))) = (heap.pop(), heap.pop()) {
+        let frequency =
+            left_frequency.checked_add(right_frequency).ok_or_else(|| invalid_data("HUFF symbol counts overflow"))?;
+        heap.push(Reverse((frequency, order, nodes.len())));
+        nodes.push(Node::Branch(left, right));
+        order += 1;
+    }
+    Ok(nodes)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn read_all(input: &[u8]) -> io::Result<Vec<u8>> {
+        let mut output = Vec::new();
+        decompress(input)?.read_to_end(&mut output)?;
+        Ok(output)
+    }
+
+    /// HUFF encoding of "abracadabra\n" as written by the huffman tool
+    fn huff_abracadabra() -> Vec<u8> {
+        let frequencies = [(b'\n', 1), (b'a', 5), (b'b', 2), (b'c', 1), (b'd', 1), (b'r', 2)];
+        let mut header = b"HUFF".to_vec();
+        header.extend((frequencies.len() as u16).to_be_bytes());
+        for (symbol, count) in frequencies {
+            header.push(symbol);
+            header.extend((count as u64).to_be_bytes());
+        }
+
+        // Codes of the tree built with ties broken by symbol:
+        // a 0, d 100, b 101, r 110, \n 1110, c 1111
+        let bits = "0 101 110 0 1111 0 100 0 101 110 0 1110".replace(' ', "");
+        header.extend((bits.len() as u64).to_be_bytes());
+        for byte in bits.as_bytes().chunks(8) {
+            let value = byte.iter().fold(0u8, |value, &bit| value << 1 | (bit - b'0'));
+            header.push(value << (8 - byte.len()));
+        }
+        header
+    }
+
+    #[test]
+    fn test_gzip_members_are_concatenated() {
+        let mut gzip = Vec::new();
+        for part in ["first line\n", "second line\n"] {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(part.as_bytes()).unwrap();
+            gzip.extend(encoder.finish().unwrap());
+        }
+
+        assert_eq!(Compression::detect(&gzip), Some(Compression::Gzip));
+        assert_eq!(read_all(&gzip).unwrap(), b"first line\nsecond line\n");
+    }
+
+    #[test]
+    fn test_huff_container() {
+        let huff = huff_abracadabra();
+
+        assert_eq!(Compression::detect(&huff), Some(Compression::Huff));
+        assert_eq!(read_all(&huff).unwrap(), b"abracadabra\n");
+        assert_eq!(read_all(&huff[..huff.len() - 1]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_huff_sample_from_the_huffman_tool() {
+        let huff = include_bytes!("../../huffman/compressed.huff");
+        assert_eq!(read_all(huff).unwrap(), include_bytes!("../../huffman/test.txt"));
+    }
+
+    #[test]
+    fn test_huff_with_a_single_symbol_has_no_bits() {
+        let mut huff = b"HUFF\x00\x01x".to_vec();
+        huff.extend(3u64.to_be_bytes());
+        huff.extend(0u64.to_be_bytes());
+
+        assert_eq!(read_all(&huff).unwrap(), b"xxx");
+    }
+
+    #[test]
+    fn test_huff_mismatching_table_is_rejected() {
+        let mut huff = huff_abracadabra();
+        // Claim one 'a' more than was encoded
+        huff[6 + 9 + 8] += 1;
+        assert_eq!(read_all(&huff).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Or one 'r' less, leaving bits unused
+        let mut huff = huff_abracadabra();
+        huff[6 + 5 * 9 + 8] -= 1;
+        assert_eq!(read_all(&huff).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Or counts whose sum does not fit
+        let mut huff = b"HUFF\x00\x02".to_vec();
+        for symbol in [b'x', b'y'] {
+            huff.push(symbol);
+            huff.extend(u64::MAX.to_be_bytes());
+        }
+        huff.extend(0u64.to_be_bytes());
+        assert_eq!(read_all(&huff).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_other_input_passes_through() {
+        assert_eq!(read_all(b"HU").unwrap(), b"HU");
+        assert_eq!(read_all(b"plain text\n").unwrap(), b"plain text\n");
+        assert_eq!(read_all(b"").unwrap(), b"");
+    }
+}
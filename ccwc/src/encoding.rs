@@ -0,0 +1,244 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Character encoding used to decode input for character-based counts
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+    /// Pick UTF-8 or UTF-16 from a byte order mark, falling back to UTF-8
+    Auto,
+}
+
+impl Encoding {
+    /// Names accepted by `from_str`, as shown on the command line
+    pub const NAMES: [&'static str; 5] = ["utf-8", "utf-16le", "utf-16be", "latin1", "auto"];
+
+    /// Newlines can only be found by scanning raw bytes in single-byte
+    /// compatible encodings; UTF-16 has to be decoded first
+    pub fn is_ascii_compatible(self) -> bool {
+        matches!(self, Encoding::Utf8 | Encoding::Latin1)
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "utf-16le" | "utf16le" => Ok(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(Encoding::Utf16Be),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            "auto" => Ok(Encoding::Auto),
+            _ => Err(format!("unknown encoding: {}", name)),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let index = match self {
+            Encoding::Utf8 => 0,
+            Encoding::Utf16Le => 1,
+            Encoding::Utf16Be => 2,
+            Encoding::Latin1 => 3,
+            Encoding::Auto => 4,
+        };
+        f.write_str(Encoding::NAMES[index])
+    }
+}
+
+/// Streaming decoder turning chunks of bytes into characters.
+///
+/// Each decoded character is passed to the callback as `Some(c)`; every
+/// malformed sequence is passed as a single `None`, so callers can decide how
+/// to count it instead of seeing a substituted U+FFFD. Sequences cut off at
+/// the end of a chunk are held back until the next one arrives.
+#[derive(Debug, Default, Clone)]
+pub struct Decoder {
+    encoding: Encoding,
+    pending: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn new(encoding: Encoding) -> Self {
+        Decoder { encoding, pending: Vec::new() }
+    }
+
+    pub fn decode<F: FnMut(Option<char>)>(&mut self, chunk: &[u8], mut emit: F) {
+        if self.pending.is_empty() {
+            self.decode_complete(chunk, &mut emit, false);
+        } else {
+            let mut joined = std::mem::take(&mut self.pending);
+            joined.extend_from_slice(chunk);
+            self.decode_complete(&joined, &mut emit, false);
+        }
+    }
+
+    /// Flush whatever is left over once the input has ended
+    pub fn finish<F: FnMut(Option<char>)>(&mut self, mut emit: F) {
+        let rest = std::mem::take(&mut self.pending);
+        if !rest.is_empty() {
+            self.decode_complete(&rest, &mut emit, true);
+        }
+    }
+
+    fn decode_complete<F: FnMut(Option<char>)>(&mut self, bytes: &[u8], emit: &mut F, last: bool) {
+        match self.encoding {
+            Encoding::Utf8 => self.decode_utf8(bytes, emit, last),
+            Encoding::Utf16Le => self.decode_utf16(bytes, u16::from_le_bytes, emit, last),
+            Encoding::Utf16Be => self.decode_utf16(bytes, u16::from_be_bytes, emit, last),
+            Encoding::Latin1 => bytes.iter().for_each(|&b| emit(Some(b as char))),
+            Encoding::Auto => {
+                // Wait until there are enough bytes to recognise a byte order mark
+                if bytes.len() < 3 && !last {
+                    self.pending.extend_from_slice(bytes);
+                    return;
+                }
+                self.encoding = match bytes {
+                    [0xff, 0xfe, ..] => Encoding::Utf16Le,
+                    [0xfe, 0xff, ..] => Encoding::Utf16Be,
+                    _ => Encoding::Utf8,
+                };
+                self.decode_complete(bytes, emit, last);
+            }
+        }
+    }
+
+    fn decode_utf8<F: FnMut(Option<char>)>(&mut self, mut bytes: &[u8], emit: &mut F, last: bool) {
+        loop {
+            match std::str::from_utf8(bytes) {
+                Ok(valid) => {
+                    valid.chars().for_each(|c| emit(Some(c)));
+                    return;
+                }
+                Err(error) => {
+                    let (valid, rest) = bytes.split_at(error.valid_up_to());
+                    std::str::from_utf8(valid)
+                        .expect("prefix was just validated")
+                        .chars()
+                        .for_each(|c| emit(Some(c)));
+
+                    match error.error_len() {
+                        Some(len) => {
+                            emit(None);
+                            bytes = &rest[len..];
+                        }
+                        None if last => {
+                            emit(None);
+                            return;
+                        }
+                        None => {
+                            // Incomplete sequence, wait for the next chunk
+                            self.pending.extend_from_slice(rest);
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn decode_utf16<F: FnMut(Option<char>)>(
+        &mut self,
+        bytes: &[u8],
+        unit: fn([u8; 2]) -> u16,
+        emit: &mut F,
+        last: bool,
+    ) {
+        let pairs = bytes.chunks_exact(2);
+        let odd_byte = pairs.remainder();
+        let mut units: Vec<u16> = pairs.map(|pair| unit([pair[0], pair[1]])).collect();
+
+        // Hold back a high surrogate whose partner is in the next chunk
+        let mut complete = units.len() * 2;
+        if !last && matches!(units.last(), Some(0xd800..=0xdbff)) {
+            units.pop();
+            complete -= 2;
+        }
+
+        char::decode_utf16(units).for_each(|c| emit(c.ok()));
+
+        if last {
+            if !odd_byte.is_empty() {
+                emit(None);
+            }
+        } else {
+            self.pending.extend_from_slice(&bytes[complete..]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_in_chunks(encoding: Encoding, bytes: &[u8], chunk_size: usize) -> Vec<Option<char>> {
+        let mut decoder = Decoder::new(encoding);
+        let mut chars = Vec::new();
+        for chunk in bytes.chunks(chunk_size) {
+            decoder.decode(chunk, |c| chars.push(c));
+        }
+        decoder.finish(|c| chars.push(c));
+        chars
+    }
+
+    fn utf16(text: &str, little_endian: bool) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| if little_endian { unit.to_le_bytes() } else { unit.to_be_bytes() })
+            .collect()
+    }
+
+    #[test]
+    fn test_utf16_across_every_chunk_size() {
+        let text = "a\u{10a}\n世界 🦀!";
+        let expected: Vec<_> = text.chars().map(Some).collect();
+
+        for little_endian in [true, false] {
+            let encoding = if little_endian { Encoding::Utf16Le } else { Encoding::Utf16Be };
+            let bytes = utf16(text, little_endian);
+            for chunk_size in 1..=bytes.len() {
+                assert_eq!(decode_in_chunks(encoding, &bytes, chunk_size), expected, "chunk size {}", chunk_size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_sequences_are_reported_once() {
+        assert_eq!(decode_in_chunks(Encoding::Utf8, b"a\xff\xfeb\xe4\xb8", 1), vec![Some('a'), None, None, Some('b'), None]);
+        // Unpaired surrogate followed by an odd trailing byte
+        assert_eq!(decode_in_chunks(Encoding::Utf16Le, &[0x61, 0x00, 0x00, 0xd8, 0x62, 0x00, 0x63], 3), vec![Some('a'), None, Some('b'), None]);
+    }
+
+    #[test]
+    fn test_latin1_maps_every_byte() {
+        assert_eq!(decode_in_chunks(Encoding::Latin1, b"caf\xe9", 2), vec![Some('c'), Some('a'), Some('f'), Some('é')]);
+    }
+
+    #[test]
+    fn test_auto_detects_byte_order_mark() {
+        let mut le = vec![0xff, 0xfe];
+        le.extend(utf16("hi", true));
+        let mut be = vec![0xfe, 0xff];
+        be.extend(utf16("hi", false));
+
+        for chunk_size in 1..4 {
+            assert_eq!(decode_in_chunks(Encoding::Auto, &le, chunk_size), vec![Some('\u{feff}'), Some('h'), Some('i')]);
+            assert_eq!(decode_in_chunks(Encoding::Auto, &be, chunk_size), vec![Some('\u{feff}'), Some('h'), Some('i')]);
+            assert_eq!(decode_in_chunks(Encoding::Auto, "hé".as_bytes(), chunk_size), vec![Some('h'), Some('é')]);
+        }
+        assert_eq!(decode_in_chunks(Encoding::Auto, b"h", 1), vec![Some('h')]);
+    }
+
+    #[test]
+    fn test_parse_names() {
+        for name in Encoding::NAMES {
+            assert_eq!(name.parse::<Encoding>().unwrap().to_string(), name);
+        }
+        assert!("ebcdic".parse::<Encoding>().is_err());
+    }
+}
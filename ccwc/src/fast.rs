@@ -0,0 +1,67 @@
+/// Byte with the given value repeated in every lane of a word
+const fn splat(byte: u8) -> u64 {
+    u64::from_ne_bytes([byte; 8])
+}
+
+const LOW_SEVEN: u64 = splat(0x7f);
+const HIGH_BIT: u64 = splat(0x80);
+
+/// Count `\n` bytes eight at a time
+pub fn count_newlines(bytes: &[u8]) -> usize {
+    count_byte(bytes, b'\n')
+}
+
+/// Count the bytes equal to `byte`, eight at a time.
+///
+/// Each word is XORed with a word of the wanted byte so matching lanes become zero,
+/// then a lane's high bit is set exactly when the lane was zero. Unlike the
+/// classic `haszero` trick this never reports false positives, so the set
+/// bits can be counted directly.
+pub fn count_byte(bytes: &[u8], byte: u8) -> usize {
+    let wanted = splat(byte);
+    let mut words = bytes.chunks_exact(8);
+    let mut count = 0;
+
+    for word in &mut words {
+        let x = u64::from_ne_bytes(word.try_into().unwrap()) ^ wanted;
+        let nonzero = ((x & LOW_SEVEN) + LOW_SEVEN) | x;
+        count += (!nonzero & HIGH_BIT).count_ones() as usize;
+    }
+
+    count + words.remainder().iter().filter(|&&b| b == byte).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naive(bytes: &[u8]) -> usize {
+        bytes.iter().filter(|&&b| b == b'\n').count()
+    }
+
+    #[test]
+    fn test_matches_naive_count_at_every_offset() {
+        let content: Vec<u8> = (0..=255u8).chain(b"\n\n\x0b\x8a\n\x0a\xff".iter().copied()).cycle().take(1000).collect();
+
+        for start in 0..16 {
+            assert_eq!(count_newlines(&content[start..]), naive(&content[start..]));
+        }
+    }
+
+    #[test]
+    fn test_any_byte_value() {
+        let content: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+
+        for byte in [0x00, 0x01, 0x7f, 0x80, 0xff] {
+            let naive = content[3..].iter().filter(|&&b| b == byte).count();
+            assert_eq!(count_byte(&content[3..], byte), naive, "byte {:#04x}", byte);
+        }
+    }
+
+    #[test]
+    fn test_all_newlines_and_none() {
+        assert_eq!(count_newlines(&[b'\n'; 37]), 37);
+        assert_eq!(count_newlines(&[b'a'; 37]), 0);
+        assert_eq!(count_newlines(b""), 0);
+    }
+}
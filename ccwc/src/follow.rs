@@ -0,0 +1,137 @@
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use ccwc::{CountOptions, Counter, Counts, CHUNK_SIZE};
+
+/// What happened to a followed file since the last poll
+#[derive(Debug, PartialEq, Eq)]
+pub enum Change {
+    /// New bytes were appended
+    Grew,
+    /// The file got shorter, so it was counted again from the start
+    Truncated,
+    /// The path now names a different file, e.g. after log rotation
+    Replaced,
+}
+
+/// Keeps a file open and counts only the bytes appended since the last poll
+pub struct Follower {
+    path: PathBuf,
+    options: CountOptions,
+    file: File,
+    counter: Counter,
+    offset: u64,
+}
+
+impl Follower {
+    pub fn open(path: &Path, options: CountOptions) -> io::Result<Self> {
+        let options = options.with_language_from(path);
+        let file = File::open(path)?;
+        Ok(Follower { path: path.to_path_buf(), counter: Counter::with_options(options.clone()), options, file, offset: 0 })
+    }
+
+    pub fn counts(&self) -> Counts {
+        self.counter.counts()
+    }
+
+    /// Read whatever was added since the last call
+    pub fn poll(&mut self) -> io::Result<Option<Change>> {
+        let mut change = None;
+
+        if let Ok(current) = fs::metadata(&self.path) {
+            if !same_file(&self.file.metadata()?, &current) {
+                self.file = File::open(&self.path)?;
+                self.restart();
+                change = Some(Change::Replaced);
+            }
+        }
+
+        if self.file.metadata()?.len() < self.offset {
+            self.file.seek(SeekFrom::Start(0))?;
+            self.restart();
+            change = Some(Change::Truncated);
+        }
+
+        let mut buffer = vec![0; CHUNK_SIZE];
+        loop {
+            match self.file.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => {
+                    self.counter.update(&buffer[..n]);
+                    self.offset += n as u64;
+                    change.get_or_insert(Change::Grew);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(change)
+    }
+
+    fn restart(&mut self) {
+        self.counter = Counter::with_options(self.options.clone());
+        self.offset = 0;
+    }
+}
+
+#[cfg(unix)]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+/// Without inode numbers rotation cannot be told apart from truncation
+#[cfg(not(unix))]
+fn same_file(_: &Metadata, _: &Metadata) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn append(path: &std::path::Path, content: &str) {
+        fs::OpenOptions::new().append(true).open(path).unwrap().write_all(content.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn test_counts_appended_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "one\ntwo\n").unwrap();
+
+        let mut follower = Follower::open(&path, CountOptions::default()).unwrap();
+        assert_eq!(follower.poll().unwrap(), Some(Change::Grew));
+        assert_eq!(follower.counts().lines, 2);
+        assert_eq!(follower.poll().unwrap(), None);
+
+        append(&path, "three fo");
+        assert_eq!(follower.poll().unwrap(), Some(Change::Grew));
+        append(&path, "ur\n");
+        follower.poll().unwrap();
+        assert_eq!(follower.counts().lines, 3);
+        assert_eq!(follower.counts().words, 4);
+    }
+
+    #[test]
+    fn test_truncation_and_rotation_start_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "one\ntwo\nthree\n").unwrap();
+
+        let mut follower = Follower::open(&path, CountOptions::default()).unwrap();
+        follower.poll().unwrap();
+
+        File::create(&path).unwrap().write_all(b"x\n").unwrap();
+        assert_eq!(follower.poll().unwrap(), Some(Change::Truncated));
+        assert_eq!(follower.counts().lines, 1);
+
+        fs::rename(&path, dir.path().join("app.log.1")).unwrap();
+        fs::write(&path, "a\nb\n").unwrap();
+        assert_eq!(follower.poll().unwrap(), Some(Change::Replaced));
+        assert_eq!(follower.counts().lines, 2);
+    }
+}
@@ -0,0 +1,82 @@
+use std::ffi::OsString;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use ccwc::{count_with, CountOptions, Counts};
+
+/// Count a file as it was at `revision` in the git repository holding it.
+///
+/// The blob is streamed from `git cat-file`, run in the file's directory
+/// so that paths relative to the current directory work from anywhere in
+/// the repository.
+pub fn count_at_revision(revision: &str, path: &Path, options: CountOptions) -> io::Result<Counts> {
+    let options = options.with_language_from(path);
+
+    let directory = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file"))?;
+    let mut object = OsString::from(format!("{}:./", revision));
+    object.push(name);
+
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .arg("cat-file")
+        .arg("blob")
+        .arg(object)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let counted = count_with(child.stdout.take().expect("stdout is piped"), options);
+    let mut message = String::new();
+    child.stderr.take().expect("stderr is piped").read_to_string(&mut message)?;
+    let status = child.wait()?;
+
+    if !status.success() {
+        // Keep git's own explanation, such as "path 'x' does not exist in 'HEAD'"
+        let reason = message.lines().next().unwrap_or("git cat-file failed");
+        return Err(io::Error::other(reason.trim_start_matches("fatal: ").to_string()));
+    }
+    counted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn git(directory: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(directory)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?}", args);
+    }
+
+    #[test]
+    fn test_counts_blob_at_revision() {
+        let repository = tempfile::tempdir().unwrap();
+        let file = repository.path().join("src/notes.txt");
+        fs::create_dir(file.parent().unwrap()).unwrap();
+        git(repository.path(), &["init", "-q"]);
+
+        fs::write(&file, "one\ntwo\n").unwrap();
+        git(repository.path(), &["add", "."]);
+        git(repository.path(), &["commit", "-q", "-m", "first"]);
+        fs::write(&file, "one\ntwo\nthree\n").unwrap();
+        git(repository.path(), &["commit", "-q", "-a", "-m", "second"]);
+
+        let options = CountOptions::default();
+        assert_eq!(count_at_revision("HEAD~1", &file, options.clone()).unwrap().lines, 2);
+        assert_eq!(count_at_revision("HEAD", &file, options.clone()).unwrap().lines, 3);
+
+        let error = count_at_revision("HEAD", &repository.path().join("src/missing.txt"), options).unwrap_err();
+        assert!(error.to_string().contains("missing.txt"), "{}", error);
+    }
+}
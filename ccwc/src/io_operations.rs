@@ -1,15 +1,85 @@
-use std::io::{Read, Result};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
 
-pub trait StdinOperations {
-    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize>;
+use ccwc::{count_path, count_with, CountOptions, Counts};
+
+/// Source of standard input, so tests can supply their own
+pub trait StdinOperations: Send {
+    /// Read the next bytes like `Read::read`, returning 0 at the end of input
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>;
 }
 
+/// The process's own standard input
 pub struct StdinReader;
 
 impl StdinOperations for StdinReader {
-    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
-        let stdin = std::io::stdin();
-        let mut handle = stdin.lock();
-        handle.read_to_end(buf)
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::stdin().lock().read(buf)
+    }
+}
+
+/// Bytes already in memory standing in for standard input
+impl<T: AsRef<[u8]> + Send> StdinOperations for io::Cursor<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Read::read(self, buf)
+    }
+}
+
+/// Something to count
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    File(PathBuf),
+    Stdin,
+}
+
+impl Input {
+    /// `-` stands for standard input wherever it appears, as in POSIX wc;
+    /// a file of that name can be counted as `./-`
+    pub fn from_operand(operand: &Path) -> Self {
+        if operand.as_os_str() == "-" {
+            Input::Stdin
+        } else {
+            Input::File(operand.to_path_buf())
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Opens inputs, reading standard input through `S`.
+///
+/// Standard input sits behind a lock so inputs can be counted from the
+/// worker threads of `-j`.
+pub struct Inputs<S> {
+    stdin: Mutex<S>,
+}
+
+impl<S: StdinOperations> Inputs<S> {
+    pub fn new(stdin: S) -> Self {
+        Inputs { stdin: Mutex::new(stdin) }
+    }
+
+    pub fn open(&self, input: &Input) -> io::Result<Box<dyn Read + '_>> {
+        Ok(match input {
+            Input::File(path) => Box::new(File::open(path)?),
+            Input::Stdin => Box::new(Stdin(self.stdin.lock().unwrap_or_else(|e| e.into_inner()))),
+        })
+    }
+
+    /// Count an input; large files are memory-mapped with `CountOptions::mmap`
+    pub fn count(&self, input: &Input, options: &CountOptions) -> io::Result<Counts> {
+        match input {
+            Input::File(path) => count_path(path, options.clone()),
+            Input::Stdin => count_with(self.open(input)?, options.clone()),
+        }
+    }
+}
+
+/// Standard input held for as long as it is being read
+struct Stdin<'a, S>(MutexGuard<'a, S>);
+
+impl<S: StdinOperations> Read for Stdin<'_, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::Path;
+
+/// A programming or markup language recognised by its file extension,
+/// along with the syntax needed to tell comments from code
+#[derive(Debug, PartialEq, Eq)]
+pub struct Language {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    /// Markers starting a comment that runs to the end of the line
+    pub line_comments: &'static [&'static str],
+    /// Start and end markers of block comments
+    pub block_comments: &'static [(&'static str, &'static str)],
+    /// Block comments nest, as in Rust
+    pub nested_comments: bool,
+    /// String delimiters; comment markers inside strings are not comments.
+    /// Longer delimiters must come before their prefixes.
+    pub quotes: &'static [&'static str],
+    /// `'x'` is a character literal, but a quote not closed right after one
+    /// character starts a lifetime or label, as in Rust
+    pub char_literals: bool,
+    /// Raw strings such as `r"…"` and `r#"…"#`, in which backslashes do not escape
+    pub raw_strings: bool,
+}
+
+const C_LINE: &[&str] = &["//"];
+const C_BLOCK: &[(&str, &str)] = &[("/*", "*/")];
+const HASH_LINE: &[&str] = &["#"];
+const XML_BLOCK: &[(&str, &str)] = &[("<!--", "-->")];
+const DOUBLE_QUOTE: &[&str] = &["\""];
+const BOTH_QUOTES: &[&str] = &["\"", "'"];
+
+impl Language {
+    const fn new(name: &'static str, extensions: &'static [&'static str]) -> Self {
+        Language {
+            name,
+            extensions,
+            line_comments: &[],
+            block_comments: &[],
+            nested_comments: false,
+            quotes: &[],
+            char_literals: false,
+            raw_strings: false,
+        }
+    }
+
+    const fn comments(
+        self,
+        line_comments: &'static [&'static str],
+        block_comments: &'static [(&'static str, &'static str)],
+    ) -> Self {
+        Language { line_comments, block_comments, ..self }
+    }
+
+    const fn nested(self) -> Self {
+        Language { nested_comments: true, ..self }
+    }
+
+    const fn quotes(self, quotes: &'static [&'static str]) -> Self {
+        Language { quotes, ..self }
+    }
+
+    const fn char_literals(self) -> Self {
+        Language { char_literals: true, ..self }
+    }
+
+    const fn raw_strings(self) -> Self {
+        Language { raw_strings: true, ..self }
+    }
+}
+
+/// Every language ccwc knows about
+pub const LANGUAGES: &[Language] = &[
+    Language::new("C", &["c", "h"]).comments(C_LINE, C_BLOCK).quotes(BOTH_QUOTES),
+    Language::new("C++", &["cc", "cpp", "cxx", "hh", "hpp", "hxx"]).comments(C_LINE, C_BLOCK).quotes(BOTH_QUOTES),
+    Language::new("C#", &["cs"]).comments(C_LINE, C_BLOCK).quotes(BOTH_QUOTES),
+    Language::new("CSS", &["css"]).comments(&[], C_BLOCK).quotes(BOTH_QUOTES),
+    Language::new("Go", &["go"]).comments(C_LINE, C_BLOCK).quotes(&["\"", "'", "`"]),
+    Language::new("HTML", &["htm", "html"]).comments(&[], XML_BLOCK),
+    Language::new("Java", &["java"]).comments(C_LINE, C_BLOCK).quotes(&["\"\"\"", "\"", "'"]),
+    Language::new("JavaScript", &["cjs", "js", "jsx", "mjs"]).comments(C_LINE, C_BLOCK).quotes(&["\"", "'", "`"]),
+    Language::new("JSON", &["json"]).quotes(DOUBLE_QUOTE),
+    Language::new("Kotlin", &["kt", "kts"]).comments(C_LINE, C_BLOCK).nested().quotes(&["\"\"\"", "\"", "'"]),
+    Language::new("Markdown", &["md", "markdown"]).comments(&[], XML_BLOCK),
+    Language::new("Python", &["py", "pyi"]).comments(HASH_LINE, &[]).quotes(&["\"\"\"", "'''", "\"", "'"]),
+    Language::new("Ruby", &["rb"]).comments(HASH_LINE, &[]).quotes(BOTH_QUOTES),
+    // Single quotes are not string delimiters since they also start lifetimes
+    Language::new("Rust", &["rs"]).comments(C_LINE, C_BLOCK).nested().quotes(DOUBLE_QUOTE).char_literals().raw_strings(),
+    Language::new("Shell", &["bash", "sh", "zsh"]).comments(HASH_LINE, &[]).quotes(BOTH_QUOTES),
+    Language::new("SQL", &["sql"]).comments(&["--"], C_BLOCK).quotes(&["'"]),
+    Language::new("Swift", &["swift"]).comments(C_LINE, C_BLOCK).nested().quotes(&["\"\"\"", "\""]),
+    Language::new("Plain Text", &["txt"]),
+    Language::new("TOML", &["toml"]).comments(HASH_LINE, &[]).quotes(&["\"\"\"", "'''", "\"", "'"]),
+    Language::new("TypeScript", &["ts", "tsx"]).comments(C_LINE, C_BLOCK).quotes(&["\"", "'", "`"]),
+    Language::new("YAML", &["yaml", "yml"]).comments(HASH_LINE, &[]).quotes(BOTH_QUOTES),
+];
+
+impl Language {
+    /// Look up a language by extension, ignoring case
+    pub fn from_extension(extension: &str) -> Option<&'static Language> {
+        let extension = extension.to_ascii_lowercase();
+        LANGUAGES.iter().find(|language| language.extensions.contains(&extension.as_str()))
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<&'static Language> {
+        Language::from_extension(path.as_ref().extension()?.to_str()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_by_extension_and_path() {
+        assert_eq!(Language::from_extension("RS").unwrap().name, "Rust");
+        assert_eq!(Language::from_path("src/main.py").unwrap().name, "Python");
+        assert_eq!(Language::from_path("Makefile"), None);
+        assert_eq!(Language::from_path("notes.unknown"), None);
+    }
+
+    #[test]
+    fn test_extensions_are_unique_and_lowercase() {
+        let mut seen = Vec::new();
+        for extension in LANGUAGES.iter().flat_map(|language| language.extensions) {
+            assert_eq!(*extension, extension.to_ascii_lowercase());
+            assert!(!seen.contains(extension), "{} listed twice", extension);
+            seen.push(*extension);
+        }
+    }
+
+    #[test]
+    fn test_longer_quotes_come_first() {
+        for language in LANGUAGES {
+            for (i, quote) in language.quotes.iter().enumerate() {
+                let shadowed = language.quotes[..i].iter().any(|earlier| quote.starts_with(earlier));
+                assert!(!shadowed, "{}: {} is shadowed", language.name, quote);
+            }
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Counting library behind the `ccwc` binary.
+//!
+//! Input is streamed through a [`Counter`] in fixed-size chunks, so any
+//! `Read` source can be counted without holding it in memory.
+
+mod code;
+mod counter;
+mod decompress;
+mod encoding;
+mod fast;
+mod language;
+mod lines;
+mod stats;
+mod words;
+
+pub use code::{LineClassifier, LineKind};
+pub use counter::{count, count_path, count_slice, count_with, CountOptions, Counter, Counts, CHUNK_SIZE, MMAP_THRESHOLD};
+pub use decompress::{decompress, Compression, HuffDecoder};
+pub use encoding::{Decoder, Encoding};
+pub use language::{Language, LANGUAGES};
+pub use lines::LineEnding;
+pub use stats::{Bucket, LineLengths};
+pub use words::{RegexWords, UnicodeWords, WhitespaceWords, WordBoundaries, WordSplitter};
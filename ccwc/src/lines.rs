@@ -0,0 +1,132 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::fast::{count_byte, count_newlines};
+
+/// Which characters end a line for the line count
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`, as POSIX wc counts them; `\r\n` ends a line at its `\n`
+    #[default]
+    Lf,
+    /// `\r`, as in files from classic Mac OS
+    Cr,
+    /// Any of `\n`, `\r\n` and a lone `\r`, for files with mixed endings
+    Any,
+}
+
+impl LineEnding {
+    /// Names accepted by `from_str`, as shown on the command line
+    pub const NAMES: [&'static str; 3] = ["lf", "cr", "any"];
+
+    /// Whether `c` ends a line, given the character before it
+    pub fn ends_line(self, previous: Option<char>, c: char) -> bool {
+        match self {
+            LineEnding::Lf => c == '\n',
+            LineEnding::Cr => c == '\r',
+            LineEnding::Any => c == '\r' || (c == '\n' && previous != Some('\r')),
+        }
+    }
+
+    /// Whether input whose last character is `c` ends with a complete line
+    pub fn is_terminator(self, c: char) -> bool {
+        match self {
+            LineEnding::Lf => c == '\n',
+            LineEnding::Cr => c == '\r',
+            LineEnding::Any => c == '\n' || c == '\r',
+        }
+    }
+
+    /// Count the line endings in a chunk of ASCII compatible input, given
+    /// the last byte of the chunk before it
+    pub fn count(self, previous: Option<u8>, chunk: &[u8]) -> usize {
+        match self {
+            LineEnding::Lf => count_newlines(chunk),
+            LineEnding::Cr => count_byte(chunk, b'\r'),
+            LineEnding::Any => {
+                // A \r\n pair ends a single line, even when split between chunks
+                let split = previous == Some(b'\r') && chunk.first() == Some(&b'\n');
+                let pairs = chunk.windows(2).filter(|pair| pair == b"\r\n").count() + split as usize;
+                count_newlines(chunk) + count_byte(chunk, b'\r') - pairs
+            }
+        }
+    }
+}
+
+impl FromStr for LineEnding {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "lf" => Ok(LineEnding::Lf),
+            "cr" => Ok(LineEnding::Cr),
+            "any" => Ok(LineEnding::Any),
+            _ => Err(format!("unknown line ending: {}", name)),
+        }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let index = match self {
+            LineEnding::Lf => 0,
+            LineEnding::Cr => 1,
+            LineEnding::Any => 2,
+        };
+        f.write_str(LineEnding::NAMES[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_in_chunks(ending: LineEnding, content: &[u8], chunk_size: usize) -> usize {
+        let mut previous = None;
+        content
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let lines = ending.count(previous, chunk);
+                previous = chunk.last().copied();
+                lines
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_counts_every_kind_of_ending() {
+        let content = b"unix\ndos\r\nmac\rdos again\r\n\r\nend";
+
+        for chunk_size in 1..=content.len() {
+            assert_eq!(count_in_chunks(LineEnding::Lf, content, chunk_size), 4, "chunk size {}", chunk_size);
+            assert_eq!(count_in_chunks(LineEnding::Cr, content, chunk_size), 4, "chunk size {}", chunk_size);
+            assert_eq!(count_in_chunks(LineEnding::Any, content, chunk_size), 5, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn test_decoded_characters_agree_with_bytes() {
+        let text = "a\r\n\r\rb\n\nc\r";
+
+        for ending in [LineEnding::Lf, LineEnding::Cr, LineEnding::Any] {
+            let mut previous = None;
+            let lines = text
+                .chars()
+                .filter(|&c| {
+                    let ends_line = ending.ends_line(previous, c);
+                    previous = Some(c);
+                    ends_line
+                })
+                .count();
+            assert_eq!(lines, ending.count(None, text.as_bytes()), "{}", ending);
+        }
+    }
+
+    #[test]
+    fn test_names_round_trip() {
+        for name in LineEnding::NAMES {
+            assert_eq!(name.parse::<LineEnding>().unwrap().to_string(), name);
+        }
+        assert!("crlf".parse::<LineEnding>().is_err());
+    }
+}
@@ -1,116 +1,455 @@
-use std::fs;
-use std::io::{self, Read};
-use std::path::Path;
-use clap::{Parser, ArgAction};
+mod command_args;
+mod follow;
+mod git;
+mod io_operations;
+mod output;
+mod parallel;
+mod summary;
+#[cfg(test)]
+mod test;
+mod walk;
 
-/// A simple implementation of the wc command
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    /// Count bytes
-    #[arg(short = 'c', long = "bytes", action = ArgAction::SetTrue)]
-    bytes: bool,
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+use clap::Parser;
+use ccwc::{CountOptions, Counts, WordBoundaries};
+use command_args::{Args, WordMode};
+use io_operations::{Input, Inputs, StdinOperations, StdinReader};
+use output::{Column, Format, Report};
+use follow::{Change, Follower};
+use git::count_at_revision;
+use parallel::ordered_map;
+use summary::ExtensionSummary;
+use walk::{expand_operands, WalkOptions};
 
-    /// Count lines
-    #[arg(short = 'l', long = "lines", action = ArgAction::SetTrue)]
-    lines: bool,
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let inputs = Inputs::new(StdinReader);
+    match run(&args, &inputs, &mut io::stdout().lock()) {
+        Ok(code) => code,
+        Err(e) => {
+            print_error("write error", &e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Count what `args` asks for, reading standard input through `inputs` and
+/// writing the report to `out`; errors about the inputs go to stderr
+fn run<S: StdinOperations>(args: &Args, inputs: &Inputs<S>, out: &mut dyn Write) -> io::Result<ExitCode> {
+    let columns = columns(args);
+    let options = count_options(&columns, args);
+    let mut report = Report::new(args.format, columns.clone(), args.total).with_stats(args.stats);
+
+    let mut files = match &args.files0_from {
+        Some(source) => match read_files0_from(inputs, source) {
+            Ok(files) => files,
+            Err(e) => {
+                print_error(&format!("cannot read file list {}", source), &e);
+                return Ok(ExitCode::FAILURE);
+            }
+        },
+        None => args.files.clone(),
+    };
+
+    let mut succeeded = true;
+    if args.files0_from.as_deref() == Some("-") && files.iter().any(|file| Input::from_operand(file) == Input::Stdin) {
+        // Standard input is already taken up by the list itself
+        eprintln!("ccwc: when reading file names from standard input, no file name of '-' allowed");
+        files.retain(|file| Input::from_operand(file) != Input::Stdin);
+        succeeded = false;
+    }
+    if args.recursive {
+        if files.is_empty() && args.files0_from.is_none() {
+            files.push(PathBuf::from("."));
+        }
+        let walk_options = match WalkOptions::new(&args.include, &args.exclude, !args.no_ignore) {
+            Ok(walk_options) => walk_options,
+            Err(e) => {
+                eprintln!("ccwc: {}", e);
+                return Ok(ExitCode::FAILURE);
+            }
+        };
+        files = expand_operands(&files, &walk_options, |operand, e| {
+            print_error(&operand.to_string_lossy(), e);
+            succeeded = false;
+        });
+    }
 
-    /// Count words
-    #[arg(short = 'w', long = "words", action = ArgAction::SetTrue)]
-    words: bool,
+    if args.follow {
+        return follow_file(&mut report, &files, &options, args.interval, out);
+    }
+    if args.diff {
+        return diff_files(&mut report, &files, &args.git_rev, inputs, &options, out);
+    }
 
-    /// Count characters
-    #[arg(short = 'm', long = "chars", action = ArgAction::SetTrue)]
-    chars: bool,
+    let revision = match args.git_rev.as_slice() {
+        [] => None,
+        [_] if files.is_empty() => {
+            eprintln!("ccwc: --git-rev needs file operands");
+            return Ok(ExitCode::FAILURE);
+        }
+        [revision] => Some(revision),
+        _ => {
+            eprintln!("ccwc: --git-rev can only be given twice with --diff");
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+
+    // Process files or stdin based on arguments
+    if files.is_empty() && args.files0_from.is_none() {
+        if let Some(header) = report.header() {
+            writeln!(out, "{}", header)?;
+        }
+        let Some(counts) = process_stdin(inputs, &options) else {
+            return Ok(ExitCode::FAILURE);
+        };
+        report.fit(&counts);
+        if report.prints_rows() {
+            writeln!(out, "{}", report.row(None, &counts))?;
+        }
+        if report.prints_total(1) {
+            writeln!(out, "{}", report.total(&counts))?;
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut summary = args.by_extension.then(ExtensionSummary::new);
+    if let Some(header) = if summary.is_some() { report.summary_header() } else { report.header() } {
+        writeln!(out, "{}", header)?;
+    }
+
+    // Text rows wait until the widest count is known
+    let mut rows = Vec::new();
+    let mut total = Counts::default();
+    let mut counted = 0;
+    let mut written = Ok(());
+    ordered_map(&files, args.jobs as usize, |file| count_file(revision, file, inputs, &options), |file, result| {
+        let file = &label(revision, file);
+        let Some(counts) = report_failure(file, result) else {
+            succeeded = false;
+            return;
+        };
+        total.add(&counts);
+        counted += 1;
+        match &mut summary {
+            Some(summary) => summary.add(file, &counts),
+            None if !report.prints_rows() => {}
+            None if report.is_aligned() => rows.push((file.clone(), counts)),
+            // Keep counting after a failed write, the error is reported once at the end
+            None if written.is_err() => {}
+            None => written = writeln!(out, "{}", report.row(Some(file), &counts)),
+        }
+    });
+    written?;
 
-    /// File to process, if omitted reads from stdin
-    file: Option<String>,
+    if let Some(summary) = summary {
+        let groups = if report.prints_rows() { summary.into_groups(columns.first().copied()) } else { Vec::new() };
+        let print_total = report.prints_total(groups.len());
+        for group in &groups {
+            report.fit(&group.counts);
+            report.fit_number(group.files);
+        }
+        if print_total {
+            report.fit(&total);
+            report.fit_number(counted);
+        }
+
+        for group in &groups {
+            writeln!(out, "{}", report.summary_row(group))?;
+        }
+        if print_total {
+            writeln!(out, "{}", report.summary_total(counted, &total))?;
+        }
+    } else {
+        let print_total = report.prints_total(files.len());
+        for (_, counts) in &rows {
+            report.fit(counts);
+        }
+        if print_total {
+            report.fit(&total);
+        }
+
+        for (file, counts) in &rows {
+            writeln!(out, "{}", report.row(Some(file), counts))?;
+        }
+        if print_total {
+            writeln!(out, "{}", report.total(&total))?;
+        }
+    }
+
+    Ok(exit_code(succeeded))
 }
 
-fn main() {
-    let args = Args::parse();
-    
-    // If no counting options are specified, default to lines, words, and bytes
-    let default_mode = !args.bytes && !args.lines && !args.words && !args.chars;
-    
-    // Process file or stdin based on arguments
-    match &args.file {
-        Some(file) => process_file(&args, file, default_mode),
-        None => process_stdin(&args, default_mode),
-    }
-}
-
-fn process_file(args: &Args, filename: &str, default_mode: bool) {
-    let path = Path::new(filename);
-    
-    match fs::read(path) {
-        Ok(content) => {
-            let content_str = String::from_utf8_lossy(&content);
-            
-            let mut output = String::new();
-            
-            if args.lines || default_mode {
-                let lines = content_str.lines().count();
-                output.push_str(&format!("{:8}", lines));
-            }
-            
-            if args.words || default_mode {
-                let words = content_str.split_whitespace().count();
-                output.push_str(&format!("{:8}", words));
-            }
-            
-            if args.bytes || default_mode {
-                let bytes = content.len();
-                output.push_str(&format!("{:8}", bytes));
+/// Print the counts of two inputs followed by their difference
+fn diff_files<S: StdinOperations>(
+    report: &mut Report,
+    files: &[PathBuf],
+    revisions: &[String],
+    inputs: &Inputs<S>,
+    options: &CountOptions,
+    out: &mut dyn Write,
+) -> io::Result<ExitCode> {
+    let sides = match (revisions, files) {
+        ([], [before, after]) => [(None, before), (None, after)],
+        ([revision], [file]) => [(Some(revision), file), (None, file)],
+        ([before, after], [file]) => [(Some(before), file), (Some(after), file)],
+        _ => {
+            eprintln!("ccwc: --diff needs two files, or one file and one or two --git-rev");
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+
+    let mut rows = Vec::new();
+    for (revision, file) in sides {
+        let label = label(revision, file);
+        let Some(counts) = report_failure(&label, count_file(revision, file, inputs, options)) else {
+            return Ok(ExitCode::FAILURE);
+        };
+        report.fit(&counts);
+        rows.push((label, counts));
+    }
+    let [(_, before), (_, after)] = &rows[..] else {
+        unreachable!("both sides were counted");
+    };
+    report.fit_diff(before, after);
+
+    if let Some(header) = report.header() {
+        writeln!(out, "{}", header)?;
+    }
+    for (label, counts) in &rows {
+        writeln!(out, "{}", report.row(Some(label), counts))?;
+    }
+    for line in report.diff(before, after) {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Count a file from disk or standard input, or a file as it was at a git revision
+fn count_file<S: StdinOperations>(
+    revision: Option<&String>,
+    file: &Path,
+    inputs: &Inputs<S>,
+    options: &CountOptions,
+) -> io::Result<Counts> {
+    // Rejected up front, as opening it would only report a missing file
+    if file.as_os_str().is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid zero-length file name"));
+    }
+    match (revision, Input::from_operand(file)) {
+        (Some(_), Input::Stdin) => Err(io::Error::new(io::ErrorKind::InvalidInput, "standard input has no git revisions")),
+        (Some(revision), _) => count_at_revision(revision, file, options.clone()),
+        (None, input) => inputs.count(&input, options),
+    }
+}
+
+/// Name shown for a file, `REV:path` when counted at a revision
+fn label(revision: Option<&String>, file: &Path) -> String {
+    match revision {
+        Some(revision) => format!("{}:{}", revision, file.display()),
+        None => file.to_string_lossy().into_owned(),
+    }
+}
+
+/// Follow a single growing file until interrupted, printing its counts
+/// whenever new data arrives
+fn follow_file(
+    report: &mut Report,
+    files: &[PathBuf],
+    options: &CountOptions,
+    interval: f64,
+    out: &mut dyn Write,
+) -> io::Result<ExitCode> {
+    let [file] = files else {
+        eprintln!("ccwc: --follow needs exactly one file");
+        return Ok(ExitCode::FAILURE);
+    };
+    if Input::from_operand(file) == Input::Stdin {
+        eprintln!("ccwc: --follow cannot follow standard input");
+        return Ok(ExitCode::FAILURE);
+    }
+    let interval = match Duration::try_from_secs_f64(interval) {
+        Ok(interval) => interval,
+        Err(_) => {
+            eprintln!("ccwc: invalid interval: {}", interval);
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+    if let Some(header) = report.header() {
+        writeln!(out, "{}", header)?;
+    }
+
+    let name = file.to_string_lossy();
+    let mut follower = match Follower::open(file, options.clone()) {
+        Ok(follower) => follower,
+        Err(e) => {
+            print_error(&name, &e);
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+
+    loop {
+        match follower.poll() {
+            Ok(None) => {}
+            Ok(Some(change)) => {
+                match change {
+                    Change::Truncated => eprintln!("ccwc: {}: file truncated", name),
+                    Change::Replaced => eprintln!("ccwc: {}: file replaced, following new file", name),
+                    Change::Grew => {}
+                }
+                let counts = follower.counts();
+                report.fit(&counts);
+                writeln!(out, "{}", report.row(Some(&name), &counts))?;
             }
-            
-            if args.chars {
-                let chars = content_str.chars().count();
-                output.push_str(&format!("{:8}", chars));
+            Err(e) => {
+                print_error(&name, &e);
+                return Ok(ExitCode::FAILURE);
             }
-            
-            output.push_str(&format!(" {}", filename));
-            println!("{}", output);
-        },
-        Err(_) => {
-            eprintln!("Could not read file: {}", filename);
         }
+        thread::sleep(interval);
+    }
+}
+
+/// Exit with status 1 if any operand could not be counted
+fn exit_code(succeeded: bool) -> ExitCode {
+    if succeeded {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
     }
 }
 
-fn process_stdin(args: &Args, default_mode: bool) {
-    let stdin = io::stdin();
-    let mut bytes = Vec::new();
-    
-    // Read all stdin as bytes
-    {
-        let mut handle = stdin.lock();
-        handle.read_to_end(&mut bytes).unwrap();
+/// Print an error with the operating system's reason, like `ccwc: a.txt: Permission denied`
+fn print_error(subject: &str, error: &io::Error) {
+    // Drop the "(os error N)" suffix std appends to OS errors
+    let reason = error.to_string();
+    let reason = match (error.raw_os_error(), reason.rfind(" (os error ")) {
+        (Some(_), Some(suffix)) => &reason[..suffix],
+        _ => &reason,
+    };
+    eprintln!("ccwc: {}: {}", subject, reason);
+}
+
+/// Read a list of NUL-separated file names, as written by `find -print0`
+fn read_files0_from<S: StdinOperations>(inputs: &Inputs<S>, source: &str) -> io::Result<Vec<PathBuf>> {
+    let mut list = Vec::new();
+    inputs.open(&Input::from_operand(Path::new(source)))?.read_to_end(&mut list)?;
+
+    // The final name may or may not be followed by a NUL
+    if list.last() == Some(&0) {
+        list.pop();
     }
-    
-    let content = String::from_utf8_lossy(&bytes);
-    
-    let mut output = String::new();
-    
+    if list.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    Ok(list.split(|&b| b == 0).map(path_from_bytes).collect())
+}
+
+/// File names are kept as they are, since `find -print0` lists any name the
+/// file system allows, UTF-8 or not
+#[cfg(unix)]
+fn path_from_bytes(name: &[u8]) -> PathBuf {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(OsString::from_vec(name.to_vec()))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(name: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(name).into_owned())
+}
+
+/// Columns to print, in wc's order
+fn columns(args: &Args) -> Vec<Column> {
+    // If no counting options are specified, default to lines, words, and bytes
+    let default_mode = !args.bytes && !args.lines && !args.words && !args.chars && !args.graphemes && !args.max_line_length;
+
+    let mut columns = Vec::new();
     if args.lines || default_mode {
-        let lines = content.lines().count();
-        output.push_str(&format!("{:8}", lines));
+        columns.push(Column::Lines);
     }
-    
     if args.words || default_mode {
-        let words = content.split_whitespace().count();
-        output.push_str(&format!("{:8}", words));
+        columns.push(Column::Words);
     }
-    
     if args.bytes || default_mode {
-        let byte_count = bytes.len();
-        output.push_str(&format!("{:8}", byte_count));
+        columns.push(Column::Bytes);
     }
-    
-    if args.chars {
-        let chars = content.chars().count();
-        output.push_str(&format!("{:8}", chars));
+    // Machine-readable records carry the character count by default as well
+    if args.chars || (default_mode && args.format != Format::Text) {
+        columns.push(Column::Chars);
+    }
+    if args.graphemes {
+        columns.push(Column::Graphemes);
+    }
+    if args.max_line_length {
+        columns.push(Column::MaxLineLength);
+    }
+    if args.code {
+        columns.extend([Column::BlankLines, Column::CommentLines, Column::CodeLines]);
+    }
+    columns.extend((0..args.count_pattern.len()).map(Column::Pattern));
+    columns.extend(args.count_byte.iter().enumerate().map(|(index, &value)| Column::Byte(index, value)));
+    columns
+}
+
+fn word_boundaries(args: &Args) -> WordBoundaries {
+    match (&args.word_regex, args.word_mode) {
+        (Some(regex), _) => WordBoundaries::Regex(regex.clone()),
+        (None, WordMode::Whitespace) => WordBoundaries::Whitespace,
+        (None, WordMode::Unicode) => WordBoundaries::Unicode,
+    }
+}
+
+/// Only the selected columns need counting
+fn count_options(columns: &[Column], args: &Args) -> CountOptions {
+    CountOptions {
+        encoding: args.encoding,
+        word_boundaries: word_boundaries(args),
+        decompress: args.decompress,
+        lines: columns.contains(&Column::Lines),
+        logical_lines: args.logical_lines,
+        line_ending: args.line_endings,
+        words: columns.contains(&Column::Words),
+        chars: columns.contains(&Column::Chars),
+        max_line_length: columns.contains(&Column::MaxLineLength),
+        graphemes: columns.contains(&Column::Graphemes),
+        code: columns.contains(&Column::CodeLines),
+        line_stats: args.stats,
+        patterns: args.count_pattern.clone(),
+        byte_values: args.count_byte.clone(),
+        mmap: args.mmap,
+        language: None,
+    }
+}
+
+/// Report a failed file, handing back the counts of one that succeeded
+fn report_failure(filename: &str, result: io::Result<Counts>) -> Option<Counts> {
+    match result {
+        Ok(counts) => Some(counts),
+        // There is no name to put before the reason
+        Err(e) if filename.is_empty() => {
+            eprintln!("ccwc: {}", e);
+            None
+        }
+        Err(e) => {
+            print_error(filename, &e);
+            None
+        }
+    }
+}
+
+fn process_stdin<S: StdinOperations>(inputs: &Inputs<S>, options: &CountOptions) -> Option<Counts> {
+    // Stream stdin through the counter instead of buffering all of it
+    match inputs.count(&Input::Stdin, options) {
+        Ok(counts) => Some(counts),
+        Err(e) => {
+            print_error("standard input", &e);
+            None
+        }
     }
-    
-    println!("{}", output);
 }
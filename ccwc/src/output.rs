@@ -0,0 +1,565 @@
+use ccwc::{Counts, LineLengths};
+use clap::ValueEnum;
+
+use crate::summary::Group;
+
+/// How count rows are written to stdout
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Padded columns like wc
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+    /// Tab-separated values with a header row
+    Tsv,
+}
+
+/// When to print the total row
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TotalMode {
+    /// With more than one input for text, always for the other formats
+    #[default]
+    Auto,
+    Always,
+    /// Print the total alone, without a row per input
+    Only,
+    Never,
+}
+
+/// A single count that can be selected for output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Lines,
+    Words,
+    Bytes,
+    Chars,
+    Graphemes,
+    MaxLineLength,
+    BlankLines,
+    CommentLines,
+    CodeLines,
+    /// Matches of the `--count-pattern` at this index
+    Pattern(usize),
+    /// Occurrences of the `--count-byte` at this index, with its value
+    Byte(usize, u8),
+}
+
+impl Column {
+    pub fn name(self) -> String {
+        match self {
+            Column::Lines => "lines".to_string(),
+            Column::Words => "words".to_string(),
+            Column::Bytes => "bytes".to_string(),
+            Column::Chars => "chars".to_string(),
+            Column::Graphemes => "graphemes".to_string(),
+            Column::MaxLineLength => "max_line_length".to_string(),
+            Column::BlankLines => "blank".to_string(),
+            Column::CommentLines => "comment".to_string(),
+            Column::CodeLines => "code".to_string(),
+            Column::Pattern(index) => format!("pattern_{}", index + 1),
+            Column::Byte(_, value) => format!("byte_{:02x}", value),
+        }
+    }
+
+    pub fn value(self, counts: &Counts) -> usize {
+        match self {
+            Column::Lines => counts.lines,
+            Column::Words => counts.words,
+            Column::Bytes => counts.bytes,
+            Column::Chars => counts.chars,
+            Column::Graphemes => counts.graphemes,
+            Column::MaxLineLength => counts.max_line_length,
+            Column::BlankLines => counts.blank_lines,
+            Column::CommentLines => counts.comment_lines,
+            Column::CodeLines => counts.code_lines,
+            Column::Pattern(index) => counts.pattern_counts.get(index).copied().unwrap_or(0),
+            Column::Byte(index, _) => counts.byte_counts.get(index).copied().unwrap_or(0),
+        }
+    }
+}
+
+/// Buckets and width in characters of the line length histogram
+const HISTOGRAM_BUCKETS: usize = 10;
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// Names of the line length statistics in the delimited formats
+const STATS_FIELDS: [&str; 5] = ["line_min", "line_max", "line_mean", "line_median", "line_p95"];
+
+/// Formats rows of counts for the selected columns.
+///
+/// Like GNU wc, text columns share one width, that of the largest number
+/// printed; rows have to be passed to `fit` before any of them is formatted.
+pub struct Report {
+    format: Format,
+    columns: Vec<Column>,
+    total: TotalMode,
+    stats: bool,
+    width: usize,
+}
+
+impl Report {
+    pub fn new(format: Format, columns: Vec<Column>, total: TotalMode) -> Self {
+        Report { format, columns, total, stats: false, width: 1 }
+    }
+
+    /// Follow every row with line length statistics: a histogram in text,
+    /// an object in JSON and extra fields in the delimited formats
+    pub fn with_stats(mut self, stats: bool) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Text rows can only be printed once every count is known
+    pub fn is_aligned(&self) -> bool {
+        self.format == Format::Text
+    }
+
+    /// Whether each input gets a row of its own
+    pub fn prints_rows(&self) -> bool {
+        self.total != TotalMode::Only
+    }
+
+    /// Whether a total follows the rows for `inputs` inputs; machine-readable
+    /// formats end with one by default, text only when there is something to add up
+    pub fn prints_total(&self, inputs: usize) -> bool {
+        match self.total {
+            TotalMode::Auto => inputs > 1 || self.format != Format::Text,
+            TotalMode::Always | TotalMode::Only => true,
+            TotalMode::Never => false,
+        }
+    }
+
+    /// Widen the text columns to hold the selected counts
+    pub fn fit(&mut self, counts: &Counts) {
+        if let Some(largest) = self.columns.iter().map(|column| column.value(counts)).max() {
+            self.fit_number(largest);
+        }
+    }
+
+    pub fn fit_number(&mut self, number: usize) {
+        self.width = self.width.max(number.to_string().len());
+    }
+
+    /// Header line naming the columns, for the delimited formats
+    pub fn header(&self) -> Option<String> {
+        let separator = self.separator()?;
+        let mut fields = vec!["path".to_string()];
+        fields.extend(self.columns.iter().map(|column| column.name()));
+        fields.extend(self.stats_fields().iter().map(|field| field.to_string()));
+        Some(fields.join(separator))
+    }
+
+    /// Row for a single input; stdin has no name
+    pub fn row(&self, name: Option<&str>, counts: &Counts) -> String {
+        match self.format {
+            Format::Text => self.text_row(name, counts),
+            Format::Json => {
+                let path = name.map_or("null".to_string(), json_string);
+                self.json_record(&format!("\"type\":\"file\",\"path\":{}", path), counts)
+            }
+            Format::Csv => self.delimited_row(&csv_field(name.unwrap_or("")), counts),
+            Format::Tsv => self.delimited_row(&tsv_field(name.unwrap_or("")), counts),
+        }
+    }
+
+    pub fn total(&self, counts: &Counts) -> String {
+        match self.format {
+            Format::Text => self.text_row(self.total_label(), counts),
+            Format::Json => self.json_record("\"type\":\"total\"", counts),
+            _ => self.row(Some("total"), counts),
+        }
+    }
+
+    /// Widen the text columns to also hold the rows of `diff`
+    pub fn fit_diff(&mut self, before: &Counts, after: &Counts) {
+        for (delta, change) in self.diff_values(before, after) {
+            self.width = self.width.max(delta.to_string().len() + (delta >= 0) as usize);
+            self.width = self.width.max(change.map_or(3, |change| format!("{:+.1}%", change).len()));
+        }
+    }
+
+    /// Rows comparing `after` with `before` for `--diff`: the difference in
+    /// every column, then the change in percent, which is undefined for
+    /// columns that were zero before
+    pub fn diff(&self, before: &Counts, after: &Counts) -> [String; 2] {
+        let values = self.diff_values(before, after);
+        let deltas = values.iter().map(|(delta, _)| delta.to_string());
+
+        match self.format {
+            Format::Text => {
+                let deltas = values.iter().map(|(delta, _)| format!("{:+}", delta));
+                let changes = values.iter().map(|(_, change)| change.map_or("n/a".to_string(), |change| format!("{:+.1}%", change)));
+                [self.text_fields(deltas, "delta"), self.text_fields(changes, "change")]
+            }
+            Format::Json => {
+                let changes = values.iter().map(|(_, change)| change.map_or("null".to_string(), |change| format!("{:.2}", change)));
+                [self.json_fields("delta", deltas), self.json_fields("change", changes)]
+            }
+            Format::Csv | Format::Tsv => {
+                let separator = self.separator().unwrap_or(",");
+                let changes = values.iter().map(|(_, change)| change.map_or(String::new(), |change| format!("{:.2}", change)));
+                let row = |label: &str, fields: Vec<String>| [label.to_string()].into_iter().chain(fields).collect::<Vec<_>>().join(separator);
+                [row("delta", deltas.collect()), row("change", changes.collect())]
+            }
+        }
+    }
+
+    fn diff_values(&self, before: &Counts, after: &Counts) -> Vec<(i128, Option<f64>)> {
+        self.columns
+            .iter()
+            .map(|column| {
+                let (before, after) = (column.value(before), column.value(after));
+                let change = (before > 0).then(|| (after as f64 - before as f64) / before as f64 * 100.0);
+                (after as i128 - before as i128, change)
+            })
+            .collect()
+    }
+
+    fn text_fields(&self, fields: impl Iterator<Item = String>, label: &str) -> String {
+        let fields: Vec<_> = fields.map(|field| format!("{:>width$}", field, width = self.width)).collect();
+        format!("{} {}", fields.join(" "), label)
+    }
+
+    fn json_fields(&self, kind: &str, fields: impl Iterator<Item = String>) -> String {
+        let mut output = format!("{{\"type\":\"{}\"", kind);
+        for (column, field) in self.columns.iter().zip(fields) {
+            output.push_str(&format!(",\"{}\":{}", column.name(), field));
+        }
+        output.push('}');
+        output
+    }
+
+    /// Header line for the `--by-extension` summary, for the delimited formats
+    pub fn summary_header(&self) -> Option<String> {
+        let separator = self.separator()?;
+        let mut fields: Vec<_> = ["extension", "language", "files"].map(String::from).into();
+        fields.extend(self.columns.iter().map(|column| column.name()));
+        fields.extend(self.stats_fields().iter().map(|field| field.to_string()));
+        Some(fields.join(separator))
+    }
+
+    /// Row for one extension: the number of files, then the selected columns
+    pub fn summary_row(&self, group: &Group) -> String {
+        match self.format {
+            Format::Text => {
+                let label = match (group.extension.as_str(), group.language) {
+                    ("", _) => "(none)".to_string(),
+                    (extension, Some(language)) => format!(".{} ({})", extension, language),
+                    (extension, None) => format!(".{}", extension),
+                };
+                format!("{:>width$} {}", group.files, self.text_row(Some(&label), &group.counts), width = self.width)
+            }
+            Format::Json => {
+                let extension = Some(group.extension.as_str()).filter(|e| !e.is_empty());
+                let prefix = format!(
+                    "\"type\":\"extension\",\"extension\":{},\"language\":{},\"files\":{}",
+                    extension.map_or("null".to_string(), json_string),
+                    group.language.map_or("null".to_string(), json_string),
+                    group.files
+                );
+                self.json_record(&prefix, &group.counts)
+            }
+            Format::Csv | Format::Tsv => {
+                let escape = if self.format == Format::Csv { csv_field } else { tsv_field };
+                let prefix = [escape(&group.extension), escape(group.language.unwrap_or("")), group.files.to_string()];
+                self.delimited_row(&prefix.join(self.separator().unwrap_or(",")), &group.counts)
+            }
+        }
+    }
+
+    pub fn summary_total(&self, files: usize, counts: &Counts) -> String {
+        match self.format {
+            Format::Text => format!("{:>width$} {}", files, self.text_row(self.total_label(), counts), width = self.width),
+            Format::Json => self.json_record(&format!("\"type\":\"total\",\"files\":{}", files), counts),
+            Format::Csv | Format::Tsv => {
+                let separator = self.separator().unwrap_or(",");
+                let prefix = ["total", "", &files.to_string()].join(separator);
+                self.delimited_row(&prefix, counts)
+            }
+        }
+    }
+
+    fn separator(&self) -> Option<&'static str> {
+        match self.format {
+            Format::Csv => Some(","),
+            Format::Tsv => Some("\t"),
+            Format::Text | Format::Json => None,
+        }
+    }
+
+    fn stats_fields(&self) -> &'static [&'static str] {
+        if self.stats { &STATS_FIELDS } else { &[] }
+    }
+
+    /// A total printed on its own needs no label, as in GNU wc
+    fn total_label(&self) -> Option<&'static str> {
+        self.prints_rows().then_some("total")
+    }
+
+    fn text_row(&self, name: Option<&str>, counts: &Counts) -> String {
+        let values: Vec<_> = self.columns.iter().map(|column| format!("{:>width$}", column.value(counts), width = self.width)).collect();
+        let mut output = values.join(" ");
+
+        if let Some(name) = name {
+            output.push_str(&format!(" {}", name));
+        }
+        if self.stats {
+            output.push_str(&text_stats(&counts.line_lengths));
+        }
+
+        output
+    }
+
+    fn json_record(&self, prefix: &str, counts: &Counts) -> String {
+        let mut output = format!("{{{}", prefix);
+        for column in &self.columns {
+            output.push_str(&format!(",\"{}\":{}", column.name(), column.value(counts)));
+        }
+        if self.stats {
+            output.push_str(&format!(",\"line_lengths\":{}", json_stats(&counts.line_lengths)));
+        }
+        output.push('}');
+        output
+    }
+
+    fn delimited_row(&self, path: &str, counts: &Counts) -> String {
+        let separator = self.separator().unwrap_or(",");
+        let mut fields = vec![path.to_string()];
+        fields.extend(self.columns.iter().map(|column| column.value(counts).to_string()));
+        if self.stats {
+            let lengths = &counts.line_lengths;
+            let numbers = [lengths.min(), lengths.max()].into_iter().chain([lengths.median(), lengths.percentile(95.0)]);
+            let mut stats: Vec<_> = numbers.map(|number| number.map_or(String::new(), |n| n.to_string())).collect();
+            stats.insert(2, lengths.mean().map_or(String::new(), |mean| format!("{:.2}", mean)));
+            fields.extend(stats);
+        }
+        fields.join(separator)
+    }
+}
+
+/// Summary line and histogram shown below a text row
+fn text_stats(lengths: &LineLengths) -> String {
+    let (Some(min), Some(max), Some(mean), Some(median), Some(p95)) =
+        (lengths.min(), lengths.max(), lengths.mean(), lengths.median(), lengths.percentile(95.0))
+    else {
+        return "\n  no lines".to_string();
+    };
+
+    let mut output = format!("\n  line length: min {}, max {}, mean {:.1}, median {}, p95 {}", min, max, mean, median, p95);
+    let histogram = lengths.histogram(HISTOGRAM_BUCKETS);
+    let most = histogram.iter().map(|bucket| bucket.lines).max().unwrap_or(1);
+    let labels: Vec<_> = histogram.iter().map(|bucket| format!("{}-{}", bucket.start, bucket.end)).collect();
+    let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+
+    for (bucket, label) in histogram.iter().zip(labels) {
+        // Rounded up, so that every non-empty bucket shows
+        let bar = "#".repeat((bucket.lines * HISTOGRAM_WIDTH).div_ceil(most));
+        output.push_str(&format!("\n  {:>lw$} {:<bw$} {}", label, bar, bucket.lines, lw = label_width, bw = HISTOGRAM_WIDTH));
+    }
+    output
+}
+
+fn json_stats(lengths: &LineLengths) -> String {
+    let number = |value: Option<usize>| value.map_or("null".to_string(), |n| n.to_string());
+    let buckets: Vec<_> = lengths
+        .histogram(HISTOGRAM_BUCKETS)
+        .iter()
+        .map(|bucket| format!("{{\"start\":{},\"end\":{},\"lines\":{}}}", bucket.start, bucket.end, bucket.lines))
+        .collect();
+    format!(
+        "{{\"lines\":{},\"min\":{},\"max\":{},\"mean\":{},\"median\":{},\"p95\":{},\"histogram\":[{}]}}",
+        lengths.lines(),
+        number(lengths.min()),
+        number(lengths.max()),
+        lengths.mean().map_or("null".to_string(), |mean| format!("{:.2}", mean)),
+        number(lengths.median()),
+        number(lengths.percentile(95.0)),
+        buckets.join(",")
+    )
+}
+
+fn json_string(value: &str) -> String {
+    let mut output = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
+/// Quote a CSV field when it contains a delimiter, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// TSV has no quoting, so escape the characters that would break a row
+fn tsv_field(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts() -> Counts {
+        Counts { lines: 3, words: 8, bytes: 48, chars: 46, ..Counts::default() }
+    }
+
+    fn report(format: Format) -> Report {
+        let mut report = Report::new(format, vec![Column::Lines, Column::Words, Column::Bytes, Column::Chars], TotalMode::Auto);
+        report.fit(&counts());
+        report
+    }
+
+    #[test]
+    fn test_text_row() {
+        assert_eq!(report(Format::Text).row(Some("a.txt"), &counts()), " 3  8 48 46 a.txt");
+        assert_eq!(report(Format::Text).header(), None);
+    }
+
+    #[test]
+    fn test_text_columns_fit_the_largest_number() {
+        let mut report = report(Format::Text);
+        let large = Counts { bytes: 123_456_789_012, ..counts() };
+        report.fit(&large);
+
+        assert_eq!(report.row(Some("a.txt"), &counts()), "           3            8           48           46 a.txt");
+        assert_eq!(report.row(Some("big"), &large), "           3            8 123456789012           46 big");
+    }
+
+    #[test]
+    fn test_total_modes() {
+        let mut only = Report::new(Format::Text, vec![Column::Lines], TotalMode::Only);
+        only.fit(&counts());
+        assert!(!only.prints_rows() && only.prints_total(1));
+        assert_eq!(only.total(&counts()), "3");
+
+        let text = report(Format::Text);
+        assert!(!text.prints_total(1) && text.prints_total(2));
+        assert_eq!(text.total(&counts()), " 3  8 48 46 total");
+        assert!(report(Format::Json).prints_total(1));
+        assert!(!Report::new(Format::Json, vec![], TotalMode::Never).prints_total(2));
+    }
+
+    #[test]
+    fn test_json_records() {
+        let report = report(Format::Json);
+
+        assert_eq!(
+            report.row(Some("dir/\"a\".txt"), &counts()),
+            r#"{"type":"file","path":"dir/\"a\".txt","lines":3,"words":8,"bytes":48,"chars":46}"#
+        );
+        assert_eq!(report.row(None, &counts()), r#"{"type":"file","path":null,"lines":3,"words":8,"bytes":48,"chars":46}"#);
+        assert_eq!(report.total(&counts()), r#"{"type":"total","lines":3,"words":8,"bytes":48,"chars":46}"#);
+    }
+
+    #[test]
+    fn test_csv_rows() {
+        let report = report(Format::Csv);
+
+        assert_eq!(report.header().unwrap(), "path,lines,words,bytes,chars");
+        assert_eq!(report.row(Some("a,b.txt"), &counts()), "\"a,b.txt\",3,8,48,46");
+        assert_eq!(report.total(&counts()), "total,3,8,48,46");
+    }
+
+    #[test]
+    fn test_summary_rows() {
+        let group = Group { extension: "rs".to_string(), language: Some("Rust"), files: 2, counts: counts() };
+
+        assert_eq!(report(Format::Text).summary_row(&group), " 2  3  8 48 46 .rs (Rust)");
+        assert_eq!(
+            report(Format::Json).summary_row(&group),
+            r#"{"type":"extension","extension":"rs","language":"Rust","files":2,"lines":3,"words":8,"bytes":48,"chars":46}"#
+        );
+        assert_eq!(report(Format::Csv).summary_header().unwrap(), "extension,language,files,lines,words,bytes,chars");
+        assert_eq!(report(Format::Csv).summary_row(&group), "rs,Rust,2,3,8,48,46");
+        assert_eq!(report(Format::Csv).summary_total(5, &counts()), "total,,5,3,8,48,46");
+    }
+
+    #[test]
+    fn test_line_length_stats() {
+        let mut counts = counts();
+        [3, 5, 12].into_iter().for_each(|length| counts.line_lengths.record(length));
+
+        let text = report(Format::Text).with_stats(true);
+        let row = text.row(Some("a.txt"), &counts);
+        let lines: Vec<_> = row.lines().collect();
+        assert_eq!(lines[0], " 3  8 48 46 a.txt");
+        assert_eq!(lines[1], "  line length: min 3, max 12, mean 6.7, median 5, p95 12");
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[2], format!("    0-1 {:<40} 0", ""));
+        assert_eq!(lines[3], format!("    2-3 {} 1", "#".repeat(40)));
+        assert_eq!(lines[8], format!("  12-13 {} 1", "#".repeat(40)));
+
+        let csv = report(Format::Csv).with_stats(true);
+        assert_eq!(csv.header().unwrap(), "path,lines,words,bytes,chars,line_min,line_max,line_mean,line_median,line_p95");
+        assert_eq!(csv.row(Some("a.txt"), &counts), "a.txt,3,8,48,46,3,12,6.67,5,12");
+        assert_eq!(csv.row(Some("empty"), &Counts::default()), "empty,0,0,0,0,,,,,");
+
+        let json = report(Format::Json).with_stats(true).total(&counts);
+        assert!(json.starts_with(concat!(
+            r#"{"type":"total","lines":3,"words":8,"bytes":48,"chars":46,"line_lengths":{"lines":3,"min":3,"max":12,"mean":6.67,"median":5,"p95":12,"#,
+            r#""histogram":[{"start":0,"end":1,"lines":0},{"start":2,"end":3,"lines":1},"#
+        )));
+        assert!(json.ends_with(r#"{"start":12,"end":13,"lines":1}]}}"#));
+    }
+
+    #[test]
+    fn test_pattern_and_byte_columns() {
+        let counts = Counts { pattern_counts: vec![4, 0], byte_counts: vec![7], ..counts() };
+        let columns = vec![Column::Lines, Column::Pattern(0), Column::Pattern(1), Column::Byte(0, b'\t')];
+        let mut report = Report::new(Format::Csv, columns, TotalMode::Auto);
+        report.fit(&counts);
+
+        assert_eq!(report.header().unwrap(), "path,lines,pattern_1,pattern_2,byte_09");
+        assert_eq!(report.row(Some("a.txt"), &counts), "a.txt,3,4,0,7");
+    }
+
+    #[test]
+    fn test_diff_rows() {
+        let before = Counts { lines: 10, words: 0, bytes: 200, chars: 200, ..Counts::default() };
+        let after = Counts { lines: 12, words: 3, bytes: 150, chars: 200, ..Counts::default() };
+
+        let mut text = report(Format::Text);
+        text.fit(&before);
+        text.fit(&after);
+        text.fit_diff(&before, &after);
+        assert_eq!(text.diff(&before, &after), ["    +2     +3    -50     +0 delta", "+20.0%    n/a -25.0%  +0.0% change"]);
+
+        assert_eq!(
+            report(Format::Json).diff(&before, &after),
+            [
+                r#"{"type":"delta","lines":2,"words":3,"bytes":-50,"chars":0}"#,
+                r#"{"type":"change","lines":20.00,"words":null,"bytes":-25.00,"chars":0.00}"#
+            ]
+        );
+        assert_eq!(report(Format::Csv).diff(&before, &after), ["delta,2,3,-50,0", "change,20.00,,-25.00,0.00"]);
+    }
+
+    #[test]
+    fn test_tsv_rows() {
+        let report = report(Format::Tsv);
+
+        assert_eq!(report.header().unwrap(), "path\tlines\twords\tbytes\tchars");
+        assert_eq!(report.row(Some("a\tb"), &counts()), "a\\tb\t3\t8\t48\t46");
+    }
+}
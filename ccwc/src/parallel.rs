@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+/// Run `job` over every item on a pool of `jobs` worker threads.
+///
+/// Results are handed to `emit` on the calling thread in the order of
+/// `items`, as soon as every earlier item has finished, so output can be
+/// printed while later items are still being counted.
+pub fn ordered_map<T, R, J, E>(items: &[T], jobs: usize, job: J, mut emit: E)
+where
+    T: Sync,
+    R: Send,
+    J: Fn(&T) -> R + Sync,
+    E: FnMut(&T, R),
+{
+    let workers = jobs.min(items.len());
+    if workers <= 1 {
+        for item in items {
+            emit(item, job(item));
+        }
+        return;
+    }
+
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let (next, job) = (&next, &job);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                if sender.send((index, job(item))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        // Hold on to results that finished ahead of their turn
+        let mut ready = BTreeMap::new();
+        let mut emitted = 0;
+        for (index, result) in receiver {
+            ready.insert(index, result);
+            while let Some(result) = ready.remove(&emitted) {
+                emit(&items[emitted], result);
+                emitted += 1;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_results_are_emitted_in_input_order() {
+        let items: Vec<u64> = (0..32).collect();
+        let mut seen = Vec::new();
+
+        // Earlier items take longer, so workers finish out of order
+        ordered_map(
+            &items,
+            4,
+            |&item| {
+                thread::sleep(Duration::from_millis(32 - item));
+                item * 2
+            },
+            |&item, result| seen.push((item, result)),
+        );
+
+        let expected: Vec<_> = items.iter().map(|&item| (item, item * 2)).collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn test_single_job_runs_sequentially() {
+        let mut seen = Vec::new();
+        ordered_map(&["a", "b"], 1, |item| item.len(), |item, result| seen.push((*item, result)));
+
+        assert_eq!(seen, vec![("a", 1), ("b", 1)]);
+    }
+}
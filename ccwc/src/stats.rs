@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+
+/// How often each line length occurs, from which line length statistics
+/// are derived.
+///
+/// Lengths are display widths, measured like `-L` does but without
+/// resetting at carriage returns. Only distinct lengths are stored, so
+/// memory stays small however many lines there are.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LineLengths {
+    lengths: BTreeMap<usize, usize>,
+}
+
+/// Lines whose length falls within `start..=end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bucket {
+    pub start: usize,
+    pub end: usize,
+    pub lines: usize,
+}
+
+impl LineLengths {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, length: usize) {
+        *self.lengths.entry(length).or_default() += 1;
+    }
+
+    /// Fold in the lengths of another input, as done for the total line
+    pub fn add(&mut self, other: &LineLengths) {
+        for (&length, &lines) in &other.lengths {
+            *self.lengths.entry(length).or_default() += lines;
+        }
+    }
+
+    pub fn lines(&self) -> usize {
+        self.lengths.values().sum()
+    }
+
+    pub fn min(&self) -> Option<usize> {
+        self.lengths.keys().next().copied()
+    }
+
+    pub fn max(&self) -> Option<usize> {
+        self.lengths.keys().next_back().copied()
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        let lines = self.lines();
+        let sum: usize = self.lengths.iter().map(|(length, count)| length * count).sum();
+        (lines > 0).then(|| sum as f64 / lines as f64)
+    }
+
+    /// The length that `percent` percent of the lines do not exceed, using
+    /// the nearest-rank method so the result is always an actual line length
+    pub fn percentile(&self, percent: f64) -> Option<usize> {
+        let lines = self.lines();
+        let rank = ((percent / 100.0 * lines as f64).ceil() as usize).clamp(1, lines.max(1));
+        let mut seen = 0;
+        self.lengths.iter().find_map(|(&length, &count)| {
+            seen += count;
+            (seen >= rank).then_some(length)
+        })
+    }
+
+    pub fn median(&self) -> Option<usize> {
+        self.percentile(50.0)
+    }
+
+    /// Group the lengths into at most `max_buckets` buckets of equal, round
+    /// width (1, 2 or 5 times a power of ten), starting at zero
+    pub fn histogram(&self, max_buckets: usize) -> Vec<Bucket> {
+        let Some(max) = self.max() else {
+            return Vec::new();
+        };
+
+        let needed = (max + 1).div_ceil(max_buckets.max(1));
+        let mut width = 1;
+        let mut scale = 1;
+        while width < needed {
+            width = [scale * 2, scale * 5, scale * 10].into_iter().find(|&w| w >= needed).unwrap_or(scale * 10);
+            scale *= 10;
+        }
+
+        let mut buckets: Vec<_> = (0..=max / width)
+            .map(|i| Bucket { start: i * width, end: i * width + width - 1, lines: 0 })
+            .collect();
+        for (&length, &lines) in &self.lengths {
+            buckets[length / width].lines += lines;
+        }
+        buckets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lengths(values: &[usize]) -> LineLengths {
+        let mut lengths = LineLengths::new();
+        values.iter().for_each(|&length| lengths.record(length));
+        lengths
+    }
+
+    #[test]
+    fn test_statistics() {
+        let lengths = lengths(&[0, 3, 3, 10, 4, 7, 80, 5, 5, 6]);
+
+        assert_eq!((lengths.min(), lengths.max(), lengths.lines()), (Some(0), Some(80), 10));
+        assert_eq!(lengths.mean(), Some(12.3));
+        assert_eq!(lengths.median(), Some(5));
+        assert_eq!(lengths.percentile(95.0), Some(80));
+        assert_eq!(lengths.percentile(0.0), Some(0));
+    }
+
+    #[test]
+    fn test_empty() {
+        let lengths = LineLengths::new();
+
+        assert_eq!((lengths.min(), lengths.mean(), lengths.median()), (None, None, None));
+        assert!(lengths.histogram(10).is_empty());
+    }
+
+    #[test]
+    fn test_histogram_uses_round_bucket_widths() {
+        let histogram = lengths(&[0, 3, 3, 10, 4, 7, 80, 5, 5, 6]).histogram(10);
+
+        assert_eq!(histogram.len(), 9);
+        assert_eq!(histogram[0], Bucket { start: 0, end: 9, lines: 8 });
+        assert_eq!(histogram[1], Bucket { start: 10, end: 19, lines: 1 });
+        assert_eq!(histogram[8], Bucket { start: 80, end: 89, lines: 1 });
+
+        let histogram = lengths(&[1, 2, 2]).histogram(10);
+        assert_eq!(histogram.iter().map(|bucket| bucket.lines).collect::<Vec<_>>(), [0, 1, 2]);
+        assert_eq!(lengths(&[0, 150]).histogram(10)[1], Bucket { start: 20, end: 39, lines: 0 });
+    }
+
+    #[test]
+    fn test_add() {
+        let mut total = lengths(&[1, 2]);
+        total.add(&lengths(&[2, 9]));
+
+        assert_eq!(total, lengths(&[1, 2, 2, 9]));
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use ccwc::{Counts, Language};
+
+use crate::output::Column;
+
+/// Counts of all files sharing an extension
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Group {
+    /// Lowercased extension without the dot, empty for files without one
+    pub extension: String,
+    pub language: Option<&'static str>,
+    pub files: usize,
+    pub counts: Counts,
+}
+
+/// Aggregates per-file counts by file extension for `--by-extension`
+#[derive(Debug, Default)]
+pub struct ExtensionSummary {
+    groups: HashMap<String, Group>,
+}
+
+impl ExtensionSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, path: &str, counts: &Counts) {
+        let extension = Path::new(path)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        let group = self.groups.entry(extension.clone()).or_insert_with(|| Group {
+            language: Language::from_extension(&extension).map(|language| language.name),
+            extension,
+            ..Group::default()
+        });
+        group.files += 1;
+        group.counts.add(counts);
+    }
+
+    /// Groups with the largest value in `sort_by` first, ties broken by extension
+    pub fn into_groups(self, sort_by: Option<Column>) -> Vec<Group> {
+        let mut groups: Vec<Group> = self.groups.into_values().collect();
+        groups.sort_by(|a, b| {
+            let key = |group: &Group| sort_by.map_or(group.files, |column| column.value(&group.counts));
+            key(b).cmp(&key(a)).then_with(|| a.extension.cmp(&b.extension))
+        });
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: usize) -> Counts {
+        Counts { lines, ..Counts::default() }
+    }
+
+    #[test]
+    fn test_groups_by_extension_sorted_by_column() {
+        let mut summary = ExtensionSummary::new();
+        summary.add("src/main.rs", &lines(10));
+        summary.add("src/lib.RS", &lines(5));
+        summary.add("README.md", &lines(40));
+        summary.add("Makefile", &lines(3));
+
+        let groups = summary.into_groups(Some(Column::Lines));
+
+        let rows: Vec<_> = groups.iter().map(|g| (g.extension.as_str(), g.language, g.files, g.counts.lines)).collect();
+        assert_eq!(rows, [("md", Some("Markdown"), 1, 40), ("rs", Some("Rust"), 2, 15), ("", None, 1, 3)]);
+    }
+}
@@ -1,729 +1,321 @@
-#[cfg(test)]
-mod tests {
-    use std::io::Write;
-    use tempfile::NamedTempFile;
-    use crate::command_args::Args;
-    use crate::io_operations::StdinOperations;
-    use crate::{process_file, process_stdin};
-    use mockall::mock;
-
-    // Create mock for StdinOperations
-    mock! {
-        StdinReader {}
-        impl StdinOperations for StdinReader {
-            fn read_to_end(&mut self, buf: &mut Vec<u8>) -> std::io::Result<usize>;
-        }
-    }
+//! End-to-end tests of the binary: arguments are parsed like on the command
+//! line, standard input is injected and the report is captured.
 
-    // Helper function to create a test file
-    fn create_test_file() -> (NamedTempFile, String) {
-        let mut temp_file = NamedTempFile::new().unwrap();
-        let content = "Hello, world!\nThis is a test file.\nThird line.\n";
-        write!(temp_file, "{}", content).unwrap();
-        (temp_file, content.to_string())
-    }
+use std::io::{self, Cursor, Read, Write};
+use std::process::ExitCode;
 
-    // PART 1: Tests for process_file function
-    #[test]
-    fn test_process_file_bytes() {
-        let (temp_file, content) = create_test_file();
-        let file_path = temp_file.path().to_str().unwrap();
-        
-        let args = Args {
-            bytes: true,
-            lines: false,
-            words: false,
-            chars: false,
-            file: Some(file_path.to_string()),
-        };
-        
-        let output = process_file(&args, file_path, false);
-        
-        assert!(output.contains(&format!("{:8}", content.len())));
-        assert!(output.contains(file_path));
-    }
+use clap::Parser;
+use mockall::mock;
+use tempfile::NamedTempFile;
 
-    #[test]
-    fn test_process_file_lines() {
-        let (temp_file, content) = create_test_file();
-        let file_path = temp_file.path().to_str().unwrap();
-        let line_count = content.lines().count();
-        
-        let args = Args {
-            bytes: false,
-            lines: true,
-            words: false,
-            chars: false,
-            file: Some(file_path.to_string()),
-        };
-        
-        let output = process_file(&args, file_path, false);
-        
-        assert!(output.contains(&format!("{:8}", line_count)));
-        assert!(output.contains(file_path));
-    }
+use crate::command_args::Args;
+use crate::io_operations::{Inputs, StdinOperations};
+use crate::run;
 
-    #[test]
-    fn test_process_file_words() {
-        let (temp_file, content) = create_test_file();
-        let file_path = temp_file.path().to_str().unwrap();
-        let word_count = content.split_whitespace().count();
-        
-        let args = Args {
-            bytes: false,
-            lines: false,
-            words: true,
-            chars: false,
-            file: Some(file_path.to_string()),
-        };
-        
-        let output = process_file(&args, file_path, false);
-        
-        assert!(output.contains(&format!("{:8}", word_count)));
-        assert!(output.contains(file_path));
+mock! {
+    Stdin {}
+    impl StdinOperations for Stdin {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>;
     }
+}
 
-    #[test]
-    fn test_process_file_chars() {
-        let (temp_file, content) = create_test_file();
-        let file_path = temp_file.path().to_str().unwrap();
-        let char_count = content.chars().count();
-        
-        let args = Args {
-            bytes: false,
-            lines: false,
-            words: false,
-            chars: true,
-            file: Some(file_path.to_string()),
-        };
-        
-        let output = process_file(&args, file_path, false);
-        
-        assert!(output.contains(&format!("{:8}", char_count)));
-        assert!(output.contains(file_path));
-    }
+const CONTENT: &str = "Hello, world!\nThis is a test file.\nThird line.\n";
 
-    #[test]
-    fn test_process_file_default_mode() {
-        let (temp_file, content) = create_test_file();
-        let file_path = temp_file.path().to_str().unwrap();
-        let line_count = content.lines().count();
-        let word_count = content.split_whitespace().count();
-        let byte_count = content.len();
-        
-        let args = Args {
-            bytes: false,
-            lines: false,
-            words: false,
-            chars: false,
-            file: Some(file_path.to_string()),
-        };
-        
-        let output = process_file(&args, file_path, true);
-        
-        assert!(output.contains(&format!("{:8}", line_count)));
-        assert!(output.contains(&format!("{:8}", word_count)));
-        assert!(output.contains(&format!("{:8}", byte_count)));
-        assert!(output.contains(file_path));
-    }
+fn create_test_file(content: &str) -> NamedTempFile {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    temp_file.write_all(content.as_bytes()).unwrap();
+    temp_file
+}
 
-    #[test]
-    fn test_process_file_multiple_flags() {
-        let (temp_file, content) = create_test_file();
-        let file_path = temp_file.path().to_str().unwrap();
-        let line_count = content.lines().count();
-        let byte_count = content.len();
-        
-        let args = Args {
-            bytes: true,
-            lines: true,
-            words: false,
-            chars: false,
-            file: Some(file_path.to_string()),
-        };
-        
-        let output = process_file(&args, file_path, false);
-        
-        assert!(output.contains(&format!("{:8}", line_count)));
-        assert!(output.contains(&format!("{:8}", byte_count)));
-        assert!(output.contains(file_path));
-    }
-    
-    #[test]
-    fn test_process_file_nonexistent() {
-        let file_path = "nonexistent_file.txt";
-        
-        let args = Args {
-            bytes: false,
-            lines: false, 
-            words: false,
-            chars: false,
-            file: Some(file_path.to_string()),
-        };
-        
-        let output = process_file(&args, file_path, true);
-        
-        assert!(output.contains(&format!("Could not read file: {}", file_path)));
-    }
-    
-    #[test]
-    fn test_process_file_all_flags() {
-        let (temp_file, content) = create_test_file();
-        let file_path = temp_file.path().to_str().unwrap();
-        let line_count = content.lines().count();
-        let word_count = content.split_whitespace().count();
-        let byte_count = content.len();
-        let char_count = content.chars().count();
-        
-        let args = Args {
-            bytes: true,
-            lines: true,
-            words: true,
-            chars: true,
-            file: Some(file_path.to_string()),
-        };
-        
-        let output = process_file(&args, file_path, false);
-        
-        assert!(output.contains(&format!("{:8}", line_count)));
-        assert!(output.contains(&format!("{:8}", word_count)));
-        assert!(output.contains(&format!("{:8}", byte_count)));
-        assert!(output.contains(&format!("{:8}", char_count)));
-        assert!(output.contains(file_path));
-    }
-    
-    // PART 2: Tests for process_stdin function using mockall
-
-    // Test default mode (no flags set)
-    #[test]
-    fn test_stdin_default_mode() {
-        let test_content = "Hello, world!\nThis is a test file.\nThird line.\n";
-        let line_count = test_content.lines().count();
-        let word_count = test_content.split_whitespace().count();
-        let byte_count = test_content.len();
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(test_content.len())
-            });
-        
-        let args = Args {
-            bytes: false,
-            lines: false,
-            words: false,
-            chars: false,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, true, mock);
-        
-        assert!(output.contains(&format!("{:8}", line_count)));
-        assert!(output.contains(&format!("{:8}", word_count)));
-        assert!(output.contains(&format!("{:8}", byte_count)));
-    }
-    
-    // Test lines flag only
-    #[test]
-    fn test_stdin_lines_only() {
-        let test_content = "Hello, world!\nThis is a test file.\nThird line.\n";
-        let line_count = test_content.lines().count();
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(test_content.len())
-            });
-        
-        let args = Args {
-            bytes: false,
-            lines: true,
-            words: false,
-            chars: false,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, false, mock);
-        
-        assert!(output.contains(&format!("{:8}", line_count)));
-        // Just check that the output has the expected count, don't check exact format
-        assert_eq!(output.trim(), format!("{:8}", line_count).trim());
-    }
-    
-    // Test words flag only
-    #[test]
-    fn test_stdin_words_only() {
-        let test_content = "Hello, world!\nThis is a test file.\nThird line.\n";
-        let word_count = test_content.split_whitespace().count();
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(test_content.len())
-            });
-        
-        let args = Args {
-            bytes: false,
-            lines: false,
-            words: true,
-            chars: false,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, false, mock);
-        
-        assert!(output.contains(&format!("{:8}", word_count)));
-        // Just check that the output has the expected count, don't check exact format
-        assert_eq!(output.trim(), format!("{:8}", word_count).trim());
-    }
-    
-    // Test bytes flag only
-    #[test]
-    fn test_stdin_bytes_only() {
-        let test_content = "Hello, world!\nThis is a test file.\nThird line.\n";
-        let byte_count = test_content.len();
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(test_content.len())
-            });
-        
-        let args = Args {
-            bytes: true,
-            lines: false,
-            words: false,
-            chars: false,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, false, mock);
-        
-        assert!(output.contains(&format!("{:8}", byte_count)));
-        // Just check that the output has the expected count, don't check exact format
-        assert_eq!(output.trim(), format!("{:8}", byte_count).trim());
-    }
-    
-    // Test chars flag only
-    #[test]
-    fn test_stdin_chars_only() {
-        let test_content = "Hello, world!\nThis is a test file.\nThird line.\n";
-        let char_count = test_content.chars().count();
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(test_content.len())
-            });
-        
-        let args = Args {
-            bytes: false,
-            lines: false,
-            words: false,
-            chars: true,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, false, mock);
-        
-        assert!(output.contains(&format!("{:8}", char_count)));
-        // Just check that the output has the expected count, don't check exact format
-        assert_eq!(output.trim(), format!("{:8}", char_count).trim());
-    }
-    
-    // Test all flags together
-    #[test]
-    fn test_stdin_all_flags() {
-        let test_content = "Hello, world!\nThis is a test file.\nThird line.\n";
-        let line_count = test_content.lines().count();
-        let word_count = test_content.split_whitespace().count();
-        let byte_count = test_content.len();
-        let char_count = test_content.chars().count();
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(test_content.len())
-            });
-        
-        let args = Args {
-            bytes: true,
-            lines: true,
-            words: true,
-            chars: true,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, false, mock);
-        
-        assert!(output.contains(&format!("{:8}", line_count)));
-        assert!(output.contains(&format!("{:8}", word_count)));
-        assert!(output.contains(&format!("{:8}", byte_count)));
-        assert!(output.contains(&format!("{:8}", char_count)));
-    }
+/// Run ccwc with `arguments`, returning its exit status and what it printed
+fn ccwc(arguments: &[&str], stdin: impl StdinOperations) -> (ExitCode, String) {
+    let args = Args::try_parse_from(["ccwc"].iter().chain(arguments)).unwrap();
+    let mut output = Vec::new();
+    let code = run(&args, &Inputs::new(stdin), &mut output).unwrap();
+    (code, String::from_utf8(output).unwrap())
+}
 
-    // Test with various flag combinations
-    
-    // Lines + Words
-    #[test]
-    fn test_stdin_lines_and_words() {
-        let test_content = "Hello, world!\nThis is a test file.\nThird line.\n";
-        let line_count = test_content.lines().count();
-        let word_count = test_content.split_whitespace().count();
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(test_content.len())
-            });
-        
-        let args = Args {
-            bytes: false,
-            lines: true,
-            words: true,
-            chars: false,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, false, mock);
-        
-        assert!(output.contains(&format!("{:8}", line_count)));
-        assert!(output.contains(&format!("{:8}", word_count)));
-        // Check content but not exact format
-        let expected = format!("{:8}{:8}", line_count, word_count);
-        assert_eq!(output.replace(" ", ""), expected.replace(" ", ""));
-    }
-    
-    // Lines + Bytes
-    #[test]
-    fn test_stdin_lines_and_bytes() {
-        let test_content = "Hello, world!\nThis is a test file.\nThird line.\n";
-        let line_count = test_content.lines().count();
-        let byte_count = test_content.len();
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(test_content.len())
-            });
-        
-        let args = Args {
-            bytes: true,
-            lines: true,
-            words: false,
-            chars: false,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, false, mock);
-        
-        assert!(output.contains(&format!("{:8}", line_count)));
-        assert!(output.contains(&format!("{:8}", byte_count)));
-        // Check content but not exact format
-        let expected = format!("{:8}{:8}", line_count, byte_count);
-        assert_eq!(output.replace(" ", ""), expected.replace(" ", ""));
-    }
-    
-    // Words + Bytes
-    #[test]
-    fn test_stdin_words_and_bytes() {
-        let test_content = "Hello, world!\nThis is a test file.\nThird line.\n";
-        let word_count = test_content.split_whitespace().count();
-        let byte_count = test_content.len();
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(test_content.len())
-            });
-        
-        let args = Args {
-            bytes: true,
-            lines: false,
-            words: true,
-            chars: false,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, false, mock);
-        
-        assert!(output.contains(&format!("{:8}", word_count)));
-        assert!(output.contains(&format!("{:8}", byte_count)));
-        // Check content but not exact format
-        let expected = format!("{:8}{:8}", word_count, byte_count);
-        assert_eq!(output.replace(" ", ""), expected.replace(" ", ""));
-    }
-    
-    // Lines + Words + Bytes
-    #[test]
-    fn test_stdin_lines_words_and_bytes() {
-        let test_content = "Hello, world!\nThis is a test file.\nThird line.\n";
-        let line_count = test_content.lines().count();
-        let word_count = test_content.split_whitespace().count();
-        let byte_count = test_content.len();
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(test_content.len())
-            });
-        
-        let args = Args {
-            bytes: true,
-            lines: true,
-            words: true,
-            chars: false,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, false, mock);
-        
-        assert!(output.contains(&format!("{:8}", line_count)));
-        assert!(output.contains(&format!("{:8}", word_count)));
-        assert!(output.contains(&format!("{:8}", byte_count)));
-        // Check content but not exact format
-        let expected = format!("{:8}{:8}{:8}", line_count, word_count, byte_count);
-        assert_eq!(output.replace(" ", ""), expected.replace(" ", ""));
-    }
-    
-    // Test with empty input
-    #[test]
-    fn test_stdin_empty_input() {
-        let test_content = "";
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(0)
-            });
-        
-        let args = Args {
-            bytes: true,
-            lines: true,
-            words: true,
-            chars: true,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, false, mock);
-        
-        assert!(output.contains(&format!("{:8}", 0))); // lines
-        assert!(output.contains(&format!("{:8}", 0))); // words
-        assert!(output.contains(&format!("{:8}", 0))); // bytes
-        assert!(output.contains(&format!("{:8}", 0))); // chars
-    }
-    
-    // Test with Unicode content to verify character count
-    #[test]
-    fn test_stdin_unicode_content() {
-        let test_content = "Hello, 世界!\nThis is a 测试 file.\nThird 行.\n";
-        let line_count = test_content.lines().count();
-        let word_count = test_content.split_whitespace().count();
-        let byte_count = test_content.len();
-        let char_count = test_content.chars().count();
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(test_content.len())
-            });
-        
-        let args = Args {
-            bytes: true,
-            lines: true,
-            words: true,
-            chars: true,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, false, mock);
-        
-        assert!(output.contains(&format!("{:8}", line_count)));
-        assert!(output.contains(&format!("{:8}", word_count)));
-        assert!(output.contains(&format!("{:8}", byte_count)));
-        assert!(output.contains(&format!("{:8}", char_count)));
-        
-        // For Unicode, byte count and char count should differ
-        assert_ne!(byte_count, char_count);
-    }
-    
-    // Test with multiline input
-    #[test]
-    fn test_stdin_multiline_input() {
-        let test_content = "First line\nSecond line\nThird line\nFourth line\nFifth line\n";
-        let line_count = test_content.lines().count();
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(test_content.len())
-            });
-        
-        let args = Args {
-            bytes: false,
-            lines: true,
-            words: false,
-            chars: false,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, false, mock);
-        
-        assert!(output.contains(&format!("{:8}", line_count)));
-        // Just check that the output has the expected count, don't check exact format
-        assert_eq!(output.trim(), format!("{:8}", line_count).trim());
-        assert_eq!(line_count, 5);
-    }
-    
-    // Test with input that has no newlines
-    #[test]
-    fn test_stdin_no_newlines() {
-        let test_content = "This is a single line with no newline characters";
-        let line_count = test_content.lines().count();
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(test_content.len())
-            });
-        
-        let args = Args {
-            bytes: false,
-            lines: true,
-            words: false,
-            chars: false,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, false, mock);
-        
-        assert!(output.contains(&format!("{:8}", line_count)));
-        // Just check that the output has the expected count, don't check exact format
-        assert_eq!(output.trim(), format!("{:8}", line_count).trim());
-        assert_eq!(line_count, 1); // Should be 1 line
-    }
-    
-    // Test with large input
-    #[test]
-    fn test_stdin_large_input() {
-        // Generate a large content string
-        let mut test_content = String::new();
-        for i in 0..1000 {
-            test_content.push_str(&format!("Line {} with some words for testing\n", i));
-        }
-        
-        let line_count = test_content.lines().count();
-        let word_count = test_content.split_whitespace().count();
-        let byte_count = test_content.len();
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(test_content.len())
-            });
-        
-        let args = Args {
-            bytes: true,
-            lines: true,
-            words: true,
-            chars: false,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, false, mock);
-        
-        assert!(output.contains(&format!("{:8}", line_count)));
-        assert!(output.contains(&format!("{:8}", word_count)));
-        assert!(output.contains(&format!("{:8}", byte_count)));
-        assert_eq!(line_count, 1000);
-    }
-    
-    // Test read error handling
-    #[test]
-    #[should_panic]
-    fn test_stdin_read_error() {
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(|_| {
-                Err(std::io::Error::new(std::io::ErrorKind::Other, "Test error"))
-            });
-        
-        let args = Args {
-            bytes: true,
-            lines: false,
-            words: false,
-            chars: false,
-            file: None,
-        };
-        
-        // This should panic due to the unwrap() on the error result
-        process_stdin(&args, false, mock);
-    }
-    
-    // Test with a mixture of flags and default mode
-    #[test]
-    fn test_stdin_mixed_flags_with_default_mode() {
-        let test_content = "Hello, world!\nThis is a test file.\nThird line.\n";
-        let line_count = test_content.lines().count();
-        let word_count = test_content.split_whitespace().count();
-        let byte_count = test_content.len();
-        let char_count = test_content.chars().count();
-        
-        let mut mock = MockStdinReader::new();
-        mock.expect_read_to_end()
-            .times(1)
-            .returning(move |buf| {
-                buf.extend_from_slice(test_content.as_bytes());
-                Ok(test_content.len())
-            });
-        
-        // Even though we have some flags set, we're passing default_mode=true
-        // which should include lines, words, and bytes regardless
-        let args = Args {
-            bytes: false,
-            lines: true,
-            words: false,
-            chars: true,
-            file: None,
-        };
-        
-        let output = process_stdin(&args, true, mock);
-        
-        // Should include lines (from flag), words (from default), bytes (from default), and chars (from flag)
-        assert!(output.contains(&format!("{:8}", line_count)));
-        assert!(output.contains(&format!("{:8}", word_count)));
-        assert!(output.contains(&format!("{:8}", byte_count)));
-        assert!(output.contains(&format!("{:8}", char_count)));
-    }
-}
\ No newline at end of file
+/// Standard input that must not be read
+fn unused_stdin() -> MockStdin {
+    let mut stdin = MockStdin::new();
+    stdin.expect_read().never();
+    stdin
+}
+
+fn process_file(flags: &[&str], path: &str) -> String {
+    let (code, output) = ccwc(&[flags, &[path]].concat(), unused_stdin());
+    assert_eq!(code, ExitCode::SUCCESS);
+    output
+}
+
+fn process_stdin(flags: &[&str], content: &str) -> String {
+    let (code, output) = ccwc(flags, Cursor::new(content.to_string()));
+    assert_eq!(code, ExitCode::SUCCESS);
+    output
+}
+
+// PART 1: file operands
+
+#[test]
+fn test_process_file_bytes() {
+    let file = create_test_file(CONTENT);
+    let path = file.path().to_str().unwrap();
+
+    assert_eq!(process_file(&["-c"], path), format!("47 {}\n", path));
+}
+
+#[test]
+fn test_process_file_lines() {
+    let file = create_test_file(CONTENT);
+    let path = file.path().to_str().unwrap();
+
+    assert_eq!(process_file(&["-l"], path), format!("3 {}\n", path));
+}
+
+#[test]
+fn test_process_file_words() {
+    let file = create_test_file(CONTENT);
+    let path = file.path().to_str().unwrap();
+
+    assert_eq!(process_file(&["-w"], path), format!("9 {}\n", path));
+}
+
+#[test]
+fn test_process_file_chars() {
+    let file = create_test_file("naïve café\n");
+    let path = file.path().to_str().unwrap();
+
+    assert_eq!(process_file(&["-m"], path), format!("11 {}\n", path));
+    assert_eq!(process_file(&["-c"], path), format!("13 {}\n", path));
+}
+
+#[test]
+fn test_process_file_default_mode() {
+    let file = create_test_file(CONTENT);
+    let path = file.path().to_str().unwrap();
+
+    assert_eq!(process_file(&[], path), format!(" 3  9 47 {}\n", path));
+}
+
+#[test]
+fn test_process_file_multiple_flags() {
+    let file = create_test_file(CONTENT);
+    let path = file.path().to_str().unwrap();
+
+    // Columns come in wc's order whatever the order of the flags
+    assert_eq!(process_file(&["-c", "-l"], path), format!(" 3 47 {}\n", path));
+}
+
+#[test]
+fn test_process_file_nonexistent() {
+    let (code, output) = ccwc(&["this/file/does/not/exist.txt"], unused_stdin());
+
+    assert_eq!(code, ExitCode::FAILURE);
+    assert_eq!(output, "");
+}
+
+#[test]
+fn test_multiple_files_with_total() {
+    let first = create_test_file(CONTENT);
+    let second = create_test_file("one two\n");
+    let (first, second) = (first.path().to_str().unwrap(), second.path().to_str().unwrap());
+
+    let (code, output) = ccwc(&[first, second], unused_stdin());
+    assert_eq!(code, ExitCode::SUCCESS);
+    assert_eq!(output, format!(" 3  9 47 {}\n 1  2  8 {}\n 4 11 55 total\n", first, second));
+
+    // Output stays in operand order when counted concurrently
+    assert_eq!(ccwc(&["-j", "2", first, second], unused_stdin()).1, output);
+}
+
+#[test]
+fn test_failed_operand_still_counts_the_rest() {
+    let file = create_test_file(CONTENT);
+    let path = file.path().to_str().unwrap();
+
+    let (code, output) = ccwc(&["-l", "missing.txt", path], unused_stdin());
+    assert_eq!(code, ExitCode::FAILURE);
+    assert_eq!(output, format!("3 {}\n3 total\n", path));
+}
+
+#[test]
+fn test_empty_file_name_is_rejected() {
+    let file = create_test_file(CONTENT);
+    let path = file.path().to_str().unwrap();
+
+    let (code, output) = ccwc(&["-l", "", path], unused_stdin());
+    assert_eq!(code, ExitCode::FAILURE);
+    assert_eq!(output, format!("3 {}\n3 total\n", path));
+}
+
+// PART 2: standard input
+
+#[test]
+fn test_stdin_default_mode() {
+    assert_eq!(process_stdin(&[], CONTENT), " 3  9 47\n");
+}
+
+#[test]
+fn test_stdin_lines_only() {
+    assert_eq!(process_stdin(&["-l"], CONTENT), "3\n");
+}
+
+#[test]
+fn test_stdin_words_only() {
+    assert_eq!(process_stdin(&["-w"], CONTENT), "9\n");
+}
+
+#[test]
+fn test_stdin_bytes_only() {
+    assert_eq!(process_stdin(&["-c"], CONTENT), "47\n");
+}
+
+#[test]
+fn test_stdin_chars_only() {
+    assert_eq!(process_stdin(&["-m"], CONTENT), "47\n");
+}
+
+#[test]
+fn test_stdin_all_flags() {
+    assert_eq!(process_stdin(&["-lwmc"], CONTENT), " 3  9 47 47\n");
+}
+
+#[test]
+fn test_stdin_lines_and_words() {
+    assert_eq!(process_stdin(&["-l", "-w"], CONTENT), "3 9\n");
+}
+
+#[test]
+fn test_stdin_empty_input() {
+    assert_eq!(process_stdin(&[], ""), "0 0 0\n");
+    assert_eq!(process_stdin(&["-m"], ""), "0\n");
+}
+
+#[test]
+fn test_stdin_unicode_content() {
+    let content = "こんにちは 世界\nHello, мир!\n";
+
+    assert_eq!(process_stdin(&["-lwmc"], content), " 2  4 38 21\n");
+}
+
+#[test]
+fn test_stdin_no_newlines() {
+    // Like POSIX wc, only newlines are counted
+    assert_eq!(process_stdin(&["-l"], "just one line"), "0\n");
+    assert_eq!(process_stdin(&["-l"], "one\ntwo"), "1\n");
+}
+
+#[test]
+fn test_logical_lines() {
+    // A final line without a newline counts as well
+    assert_eq!(process_stdin(&["-l", "--logical-lines"], "one\ntwo"), "2\n");
+    assert_eq!(process_stdin(&["-l", "--logical-lines"], CONTENT), "3\n");
+    // The last of --posix and --logical-lines wins
+    assert_eq!(process_stdin(&["-l", "--logical-lines", "--posix"], "one\ntwo"), "1\n");
+}
+
+#[test]
+fn test_line_endings() {
+    let content = "unix\ndos\r\nmac\rend";
+
+    assert_eq!(process_stdin(&["-l"], content), "2\n");
+    assert_eq!(process_stdin(&["-l", "--line-endings", "cr"], content), "2\n");
+    assert_eq!(process_stdin(&["-l", "--line-endings", "any"], content), "3\n");
+    assert_eq!(process_stdin(&["-l", "--line-endings", "any", "--logical-lines"], content), "4\n");
+}
+
+#[test]
+fn test_stdin_large_input_in_short_reads() {
+    let content = "The quick brown fox jumps over the lazy dog\n".repeat(1000);
+    let mut cursor = Cursor::new(content.clone().into_bytes());
+
+    // A pipe hands over less than was asked for, splitting lines and words
+    let mut stdin = MockStdin::new();
+    stdin.expect_read().returning(move |buf| {
+        let len = buf.len().min(999);
+        Read::read(&mut cursor, &mut buf[..len])
+    });
+
+    let (code, output) = ccwc(&[], stdin);
+    assert_eq!(code, ExitCode::SUCCESS);
+    assert_eq!(output, " 1000  9000 44000\n");
+}
+
+#[test]
+fn test_stdin_read_error() {
+    let mut stdin = MockStdin::new();
+    stdin.expect_read().times(1).returning(|_| Err(io::Error::other("Test error")));
+
+    let (code, output) = ccwc(&["-c"], stdin);
+    assert_eq!(code, ExitCode::FAILURE);
+    assert_eq!(output, "");
+}
+
+#[test]
+fn test_stdin_machine_readable() {
+    let output = process_stdin(&["--format", "csv", "-lw", "--total", "never"], CONTENT);
+    assert_eq!(output, "path,lines,words\n,3,9\n");
+}
+
+#[test]
+fn test_dash_reads_stdin_between_files() {
+    let first = create_test_file(CONTENT);
+    let second = create_test_file("one two\n");
+    let (first, second) = (first.path().to_str().unwrap(), second.path().to_str().unwrap());
+
+    let (code, output) = ccwc(&[first, "-", second], Cursor::new("a b c\n"));
+    assert_eq!(code, ExitCode::SUCCESS);
+    assert_eq!(output, format!(" 3  9 47 {}\n 1  3  6 -\n 1  2  8 {}\n 5 14 61 total\n", first, second));
+}
+
+#[test]
+fn test_dash_alone_is_named() {
+    // Unlike no operands at all, an explicit `-` gets a name
+    assert_eq!(process_stdin(&["-l", "-"], CONTENT), "3 -\n");
+    assert_eq!(process_stdin(&["-l", "--format", "csv", "--total", "never", "-"], CONTENT), "path,lines\n-,3\n");
+}
+
+#[test]
+fn test_stdin_is_read_once() {
+    // The second `-` finds standard input at its end, as with wc
+    assert_eq!(process_stdin(&["-lc", "-", "-"], CONTENT), " 3 47 -\n 0  0 -\n 3 47 total\n");
+}
+
+#[test]
+fn test_dash_in_files0_from_stdin_is_rejected() {
+    let file = create_test_file(CONTENT);
+    let path = file.path().to_str().unwrap();
+
+    let (code, output) = ccwc(&["-l", "--files0-from", "-"], Cursor::new(format!("-\0{}", path)));
+    assert_eq!(code, ExitCode::FAILURE);
+    assert_eq!(output, format!("3 {}\n", path));
+}
+
+#[test]
+fn test_files0_from_stdin() {
+    let first = create_test_file(CONTENT);
+    let second = create_test_file("one two\n");
+    let (first, second) = (first.path().to_str().unwrap(), second.path().to_str().unwrap());
+
+    let names = format!("{}\0{}\0", first, second);
+    assert_eq!(
+        process_stdin(&["-l", "--files0-from", "-"], &names),
+        format!("3 {}\n1 {}\n4 total\n", first, second)
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_files0_from_keeps_names_that_are_not_utf8() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(std::ffi::OsStr::from_bytes(b"caf\xe9.txt"));
+    std::fs::write(&path, CONTENT).unwrap();
+
+    let mut names = path.as_os_str().as_bytes().to_vec();
+    names.push(0);
+    let (code, output) = ccwc(&["-l", "--files0-from", "-"], Cursor::new(names));
+    assert_eq!(code, ExitCode::SUCCESS);
+    assert_eq!(output, format!("3 {}\n", path.to_string_lossy()));
+}
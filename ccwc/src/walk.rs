@@ -0,0 +1,171 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+
+/// Filters applied while expanding directories with `--recursive`
+#[derive(Clone)]
+pub struct WalkOptions {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    respect_ignore_files: bool,
+}
+
+impl WalkOptions {
+    /// Files must match one of `include` (if any) and none of `exclude`;
+    /// directories matching `exclude` are not descended into
+    pub fn new(include: &[Glob], exclude: &[Glob], respect_ignore_files: bool) -> Result<Self, globset::Error> {
+        let include = if include.is_empty() { None } else { Some(build_set(include)?) };
+        Ok(WalkOptions { include, exclude: build_set(exclude)?, respect_ignore_files })
+    }
+
+    fn is_excluded(&self, relative: &Path) -> bool {
+        matches(&self.exclude, relative)
+    }
+
+    fn is_included(&self, relative: &Path) -> bool {
+        self.include.as_ref().is_none_or(|include| matches(include, relative))
+    }
+}
+
+fn build_set(globs: &[Glob]) -> Result<GlobSet, globset::Error> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(glob.clone());
+    }
+    builder.build()
+}
+
+/// Patterns match either the file name or the path below the operand,
+/// so both `*.rs` and `src/*.rs` behave as expected
+fn matches(set: &GlobSet, relative: &Path) -> bool {
+    set.is_match(relative) || relative.file_name().is_some_and(|name| set.is_match(name))
+}
+
+/// Replace directory operands with the files below them, in sorted order.
+///
+/// Other operands, including `-` for standard input, are passed through
+/// untouched so that errors for them are reported when they are counted.
+/// Problems found while walking, such as unreadable subdirectories, are
+/// handed to `on_error`.
+pub fn expand_operands<E>(operands: &[PathBuf], options: &WalkOptions, mut on_error: E) -> Vec<PathBuf>
+where
+    E: FnMut(&Path, &io::Error),
+{
+    let mut files = Vec::new();
+
+    for root in operands {
+        if root.as_os_str() == "-" || !root.is_dir() {
+            files.push(root.clone());
+            continue;
+        }
+
+        // The filter has to own everything it looks at
+        let (filter_root, filter_options) = (root.clone(), options.clone());
+        let walker = WalkBuilder::new(root)
+            .standard_filters(options.respect_ignore_files)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .filter_entry(move |entry| {
+                let relative = entry.path().strip_prefix(&filter_root).unwrap_or(entry.path());
+                entry.depth() == 0 || !filter_options.is_excluded(relative)
+            })
+            .build();
+
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    let message = e.to_string();
+                    let error = e.into_io_error().unwrap_or_else(|| io::Error::other(message));
+                    on_error(root, &error);
+                    continue;
+                }
+            };
+
+            let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            if entry.file_type().is_some_and(|kind| kind.is_file()) && options.is_included(relative) {
+                files.push(entry.into_path());
+            }
+        }
+    }
+
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn glob(pattern: &str) -> Glob {
+        Glob::new(pattern).unwrap()
+    }
+
+    fn tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for file in ["a.rs", "b.txt", "src/c.rs", "src/d.md", "target/e.rs"] {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "x\n").unwrap();
+        }
+        dir
+    }
+
+    fn expand(dir: &Path, options: &WalkOptions) -> Vec<String> {
+        expand_operands(&[dir.to_path_buf()], options, |_, e| panic!("{}", e))
+            .iter()
+            .map(|file| file.strip_prefix(dir).unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_expands_directories_in_sorted_order() {
+        let dir = tree();
+        let options = WalkOptions::new(&[], &[], true).unwrap();
+
+        assert_eq!(expand(dir.path(), &options), ["a.rs", "b.txt", "src/c.rs", "src/d.md", "target/e.rs"]);
+    }
+
+    #[test]
+    fn test_include_and_exclude() {
+        let dir = tree();
+        let options = WalkOptions::new(&[glob("*.rs")], &[glob("target")], true).unwrap();
+
+        assert_eq!(expand(dir.path(), &options), ["a.rs", "src/c.rs"]);
+    }
+
+    #[test]
+    fn test_respects_gitignore() {
+        let dir = tree();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join(".gitignore"), "target/\n*.md\n").unwrap();
+
+        let options = WalkOptions::new(&[], &[], true).unwrap();
+        assert_eq!(expand(dir.path(), &options), ["a.rs", "b.txt", "src/c.rs"]);
+
+        let options = WalkOptions::new(&[], &[], false).unwrap();
+        assert_eq!(expand(dir.path(), &options).len(), 6);
+    }
+
+    #[test]
+    fn test_non_directories_pass_through() {
+        let options = WalkOptions::new(&[], &[], true).unwrap();
+        let operands = vec![PathBuf::from("missing.txt"), PathBuf::from("-")];
+
+        assert_eq!(expand_operands(&operands, &options, |_, _| {}), operands);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_names_that_are_not_utf8_are_kept() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(std::ffi::OsStr::from_bytes(b"caf\xe9.txt"));
+        fs::write(&path, "x\n").unwrap();
+
+        let options = WalkOptions::new(&[], &[], true).unwrap();
+        assert_eq!(expand_operands(&[dir.path().to_path_buf()], &options, |_, e| panic!("{}", e)), [path]);
+    }
+}
@@ -0,0 +1,372 @@
+use std::fmt;
+
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Decides where words begin and end in a stream of decoded characters.
+///
+/// Splitters that need to look at more than one character at a time may
+/// buffer text and only report the words they are sure about; `flush` is
+/// called after every chunk of input and `finish` once it has ended.
+pub trait WordSplitter: fmt::Debug + Send {
+    /// Feed one character, or `None` for a malformed sequence, returning
+    /// the number of words it completes
+    fn push(&mut self, c: Option<char>) -> usize;
+
+    /// Count the words in buffered text that later input can no longer change
+    fn flush(&mut self) -> usize {
+        0
+    }
+
+    /// Count whatever is still buffered once the input has ended
+    fn finish(&mut self) -> usize {
+        0
+    }
+
+    fn clone_box(&self) -> Box<dyn WordSplitter>;
+}
+
+impl Clone for Box<dyn WordSplitter> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// The built-in word definitions
+#[derive(Debug, Clone, Default)]
+pub enum WordBoundaries {
+    /// Runs of non-whitespace characters, as counted by wc
+    #[default]
+    Whitespace,
+    /// Words as defined by Unicode word boundaries (UAX #29), so every
+    /// CJK ideograph is a word and punctuation is not
+    Unicode,
+    /// Every non-empty match of a regular expression; matches do not span lines
+    Regex(Regex),
+}
+
+impl WordBoundaries {
+    pub fn splitter(&self) -> Box<dyn WordSplitter> {
+        match self {
+            WordBoundaries::Whitespace => Box::new(WhitespaceWords::default()),
+            WordBoundaries::Unicode => Box::new(UnicodeWords::default()),
+            WordBoundaries::Regex(regex) => Box::new(RegexWords::new(regex.clone())),
+        }
+    }
+}
+
+/// Counts a word whenever a non-whitespace character follows whitespace.
+///
+/// A malformed sequence is not a character but still belongs to the
+/// surrounding word, as in wc.
+#[derive(Debug, Default, Clone)]
+pub struct WhitespaceWords {
+    in_word: bool,
+}
+
+impl WordSplitter for WhitespaceWords {
+    fn push(&mut self, c: Option<char>) -> usize {
+        let starts_word = !self.in_word;
+        self.in_word = !c.is_some_and(char::is_whitespace);
+        (starts_word && self.in_word) as usize
+    }
+
+    fn clone_box(&self) -> Box<dyn WordSplitter> {
+        Box::new(self.clone())
+    }
+}
+
+/// Segments longer than this are counted before they end, keeping only
+/// `KEPT_TAIL` bytes of them as context for the boundary rules
+const MAX_BUFFERED: usize = 4096;
+const KEPT_TAIL: usize = 64;
+
+/// Splits text at Unicode word boundaries and counts the segments holding
+/// at least one letter or digit.
+///
+/// Holding back a long segment whole would make every chunk re-segment it,
+/// so once it outgrows `MAX_BUFFERED` it is counted and cut down to its tail.
+/// That only changes the result for runs of combining marks or regional
+/// indicators longer than `KEPT_TAIL`.
+#[derive(Debug, Default, Clone)]
+pub struct UnicodeWords {
+    text: String,
+    // The first segment of `text` has already been counted as a word
+    counted: bool,
+}
+
+fn is_word(segment: &str) -> bool {
+    segment.chars().any(char::is_alphanumeric)
+}
+
+impl UnicodeWords {
+    fn count(&mut self, last: bool) -> usize {
+        // Apostrophes and separators like the one in "can't" or "1,000" only
+        // join the segments around them once the next character is known, so
+        // the last two segments may still change with the next chunk. Only the
+        // last one is kept when the text has grown long.
+        let unsettled = match last {
+            true => 0,
+            false if self.text.len() > MAX_BUFFERED => 1,
+            false => 2,
+        };
+        let mut segments = self.text.split_word_bound_indices();
+        let unfinished = if last { None } else { segments.nth_back(unsettled - 1).map(|(start, _)| start) };
+
+        let mut settled = segments.map(|(_, segment)| is_word(segment));
+        let mut words = 0;
+        if let Some(first) = settled.next() {
+            words += (first && !self.counted) as usize + settled.filter(|&word| word).count();
+            self.counted = false;
+        }
+
+        match unfinished {
+            Some(start) => {
+                self.text.drain(..start);
+            }
+            None if last => self.text.clear(),
+            // Fewer segments than are held back, nothing is settled yet
+            None => return 0,
+        }
+
+        // What is left is a single long segment whose word status is known
+        if self.text.len() > MAX_BUFFERED {
+            words += (!self.counted && is_word(&self.text)) as usize;
+            self.counted = true;
+            let mut cut = self.text.len() - KEPT_TAIL;
+            while !self.text.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.text.drain(..cut);
+        }
+        words
+    }
+}
+
+impl WordSplitter for UnicodeWords {
+    fn push(&mut self, c: Option<char>) -> usize {
+        self.text.push(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+        0
+    }
+
+    fn flush(&mut self) -> usize {
+        self.count(false)
+    }
+
+    fn finish(&mut self) -> usize {
+        self.count(true)
+    }
+
+    fn clone_box(&self) -> Box<dyn WordSplitter> {
+        Box::new(self.clone())
+    }
+}
+
+/// Lines longer than this are matched before they end, up to a point
+/// `LOOKAHEAD` bytes before what has arrived so far
+const MAX_LINE: usize = 64 * 1024;
+const LOOKAHEAD: usize = 4 * 1024;
+
+/// Counts the non-empty matches of a regular expression, one line at a time.
+///
+/// Each line is matched on its own, without its newline, so `^` and `$`
+/// anchor to the line and the count does not depend on how the input was
+/// read. A line is held back until it ends, so that no match is cut short,
+/// unless it grows past `MAX_LINE`. Then the matches starting before the last
+/// `LOOKAHEAD` bytes are counted and dropped, so a match longer than that may
+/// be counted in pieces, as in minified code or a log without newlines.
+#[derive(Debug, Clone)]
+pub struct RegexWords {
+    regex: Regex,
+    text: String,
+    // Length of the already counted character kept at the start of `text`,
+    // so that `^` and `\b` see what came before a dropped piece of a line
+    context: usize,
+}
+
+impl RegexWords {
+    pub fn new(regex: Regex) -> Self {
+        RegexWords { regex, text: String::new(), context: 0 }
+    }
+
+    /// Iterate over the matches in `line` that start at `start` or later,
+    /// with what comes before as context
+    fn matches<'a>(&'a self, line: &'a str, start: usize) -> impl Iterator<Item = regex::Match<'a>> + 'a {
+        let mut at = Some(start);
+        std::iter::from_fn(move || {
+            let found = self.regex.find_at(line, at?)?;
+            // Step over empty matches so the search moves on
+            at = match line[found.end()..].chars().next() {
+                Some(c) if found.is_empty() => Some(found.end() + c.len_utf8()),
+                None if found.is_empty() => None,
+                _ => Some(found.end()),
+            };
+            Some(found)
+        })
+    }
+
+    fn count(&self, line: &str, start: usize) -> usize {
+        self.matches(line, start).filter(|found| !found.is_empty()).count()
+    }
+
+    /// Count the complete lines up to the newline at `end` and drop them
+    fn count_lines(&mut self, end: usize) -> usize {
+        let mut words = 0;
+        let mut start = self.context;
+        for line in self.text[..end].split('\n') {
+            words += self.count(line, start);
+            start = 0;
+        }
+        self.text.drain(..=end);
+        self.context = 0;
+        words
+    }
+
+    /// Count and drop the settled part of a line that has grown too long
+    fn count_long_line(&mut self) -> usize {
+        let mut cut = self.text.len() - LOOKAHEAD;
+        while !self.text.is_char_boundary(cut) {
+            cut += 1;
+        }
+
+        let mut words = 0;
+        let mut settled = cut;
+        for found in self.matches(&self.text, self.context).take_while(|found| found.start() < cut) {
+            words += !found.is_empty() as usize;
+            settled = settled.max(found.end());
+        }
+        self.context = self.text[..settled].chars().next_back().map_or(0, char::len_utf8);
+        self.text.drain(..settled - self.context);
+        words
+    }
+}
+
+impl WordSplitter for RegexWords {
+    fn push(&mut self, c: Option<char>) -> usize {
+        self.text.push(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+        0
+    }
+
+    fn flush(&mut self) -> usize {
+        // Only complete lines are matched, the rest waits for more input
+        let mut words = 0;
+        if let Some(end) = self.text.rfind('\n') {
+            words += self.count_lines(end);
+        }
+        if self.text.len() - self.context > MAX_LINE {
+            words += self.count_long_line();
+        }
+        words
+    }
+
+    fn finish(&mut self) -> usize {
+        let words = self.count(&self.text, self.context);
+        self.text.clear();
+        self.context = 0;
+        words
+    }
+
+    fn clone_box(&self) -> Box<dyn WordSplitter> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_words(boundaries: &WordBoundaries, text: &str, chunk_size: usize) -> usize {
+        let mut splitter = boundaries.splitter();
+        let chars: Vec<char> = text.chars().collect();
+        let mut words = 0;
+        for chunk in chars.chunks(chunk_size) {
+            words += chunk.iter().map(|&c| splitter.push(Some(c))).sum::<usize>();
+            words += splitter.flush();
+        }
+        words + splitter.finish()
+    }
+
+    #[test]
+    fn test_whitespace_words() {
+        assert_eq!(count_words(&WordBoundaries::Whitespace, "  hello,  world!\n再见 ", 3), 3);
+    }
+
+    #[test]
+    fn test_unicode_words_split_cjk_and_skip_punctuation() {
+        let text = "Hello, world! 你好世界 can't stop — 1,000\n";
+
+        for chunk_size in 1..=text.chars().count() {
+            assert_eq!(count_words(&WordBoundaries::Unicode, text, chunk_size), 9, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn test_unicode_words_without_boundaries_stay_bounded() {
+        // One word spread over many chunks, none of them holding a boundary
+        let mut splitter = UnicodeWords::default();
+        let mut words = 0;
+        for _ in 0..200 {
+            words += (0..16 * 1024).map(|_| splitter.push(Some('a'))).sum::<usize>();
+            words += splitter.flush();
+            assert!(splitter.text.len() <= MAX_BUFFERED);
+        }
+        words += " b, c".chars().map(|c| splitter.push(Some(c))).sum::<usize>();
+
+        assert_eq!(words + splitter.flush() + splitter.finish(), 3);
+    }
+
+    #[test]
+    fn test_regex_counts_identifiers() {
+        let regex = WordBoundaries::Regex(Regex::new(r"[A-Za-z_][A-Za-z0-9_]*").unwrap());
+        let text = "let total_count = add(x1, 2);\nfn add(a: u32) {}";
+
+        for chunk_size in 1..=text.chars().count() {
+            assert_eq!(count_words(&regex, text, chunk_size), 8, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn test_regex_matches_each_line_on_its_own() {
+        let text = "foo bar\nfoo\nbar foo\na\nb";
+
+        for chunk_size in 1..=text.chars().count() {
+            for (pattern, words) in [("^foo", 2), ("foo$", 2), (r"a\sb", 0), (r"\bfoo\b", 3)] {
+                let regex = WordBoundaries::Regex(Regex::new(pattern).unwrap());
+                assert_eq!(count_words(&regex, text, chunk_size), words, "{} in chunks of {}", pattern, chunk_size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_regex_anchors_on_a_line_without_end() {
+        let mut splitter = RegexWords::new(Regex::new(r"^x|\bab").unwrap());
+        let mut words = 0;
+        for _ in 0..100 {
+            words += "xab ".repeat(2000).chars().map(|c| splitter.push(Some(c))).sum::<usize>();
+            words += splitter.flush();
+        }
+
+        // Only the first x starts the line and no "ab" starts a word, however
+        // the line was cut up
+        assert_eq!(words + splitter.finish(), 1);
+    }
+
+    #[test]
+    fn test_regex_on_a_line_without_end_stays_bounded() {
+        let mut splitter = RegexWords::new(Regex::new(r"[a-z]+\d").unwrap());
+        let mut words = 0;
+        for _ in 0..100 {
+            words += "abc1 xyz2 ".repeat(2000).chars().map(|c| splitter.push(Some(c))).sum::<usize>();
+            words += splitter.flush();
+            assert!(splitter.text.len() <= MAX_LINE);
+        }
+
+        assert_eq!(words + splitter.finish(), 100 * 2 * 2000);
+    }
+
+    #[test]
+    fn test_regex_ignores_empty_matches() {
+        let regex = WordBoundaries::Regex(Regex::new(r"\d*").unwrap());
+        assert_eq!(count_words(&regex, "a1b22c", 2), 2);
+    }
+}
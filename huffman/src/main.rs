@@ -44,6 +44,9 @@ fn main() -> Result<(), Box<dyn Error>> {
 #[derive(Eq)]
 struct Node {
     freq: usize,
+    // Breaks ties between equal frequencies, so that encoder and decoder
+    // build the same tree: the symbol for leaves, 256 and up for merged nodes
+    order: usize,
     symbol: Option<u8>,
     left: Option<Box<Node>>,
     right: Option<Box<Node>>,
@@ -51,13 +54,13 @@ struct Node {
 
 impl PartialEq for Node {
     fn eq(&self, other: &Self) -> bool {
-        self.freq == other.freq
+        self.freq == other.freq && self.order == other.order
     }
 }
 
 impl Ord for Node {
     fn cmp(&self, other: &Self) -> Ordering {
-        other.freq.cmp(&self.freq)
+        other.freq.cmp(&self.freq).then(other.order.cmp(&self.order))
     }
 }
 
@@ -69,20 +72,23 @@ impl PartialOrd for Node {
 
 fn build_huffman_tree(freq_map: &HashMap<u8, usize>) -> Option<Box<Node>> {
     let mut heap: BinaryHeap<Node> = freq_map.iter()
-        .map(|(&symbol, &freq)| Node { freq, symbol: Some(symbol), left: None, right: None })
+        .map(|(&symbol, &freq)| Node { freq, order: symbol as usize, symbol: Some(symbol), left: None, right: None })
         .collect();
     if heap.is_empty() {
         return None;
     }
+    let mut order = 256;
     while heap.len() > 1 {
         let left = heap.pop().unwrap();
         let right = heap.pop().unwrap();
         heap.push(Node {
             freq: left.freq + right.freq,
+            order,
             symbol: None,
             left: Some(Box::new(left)),
             right: Some(Box::new(right)),
         });
+        order += 1;
     }
     Some(Box::new(heap.pop().unwrap()))
 }
@@ -175,4 +181,30 @@ fn decompress_file(input: &PathBuf, output: &PathBuf) -> Result<(), Box<dyn Erro
     writer.write_all(&output_data)?;
     writer.flush()?;
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let dir = std::env::temp_dir().join(format!("huffman-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (input, compressed, output) = (dir.join("input.txt"), dir.join("input.huff"), dir.join("output.txt"));
+
+        // Many symbols share a frequency, so the tree depends on how ties are broken
+        let data = "abracadabra\n".repeat(3) + "the quick brown fox jumps over the lazy dog\n";
+        std::fs::write(&input, &data).unwrap();
+        compress_file(&input, &compressed).unwrap();
+        decompress_file(&compressed, &output).unwrap();
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), data);
+
+        // The same input always encodes the same way
+        let first = std::fs::read(&compressed).unwrap();
+        compress_file(&input, &compressed).unwrap();
+        assert_eq!(std::fs::read(&compressed).unwrap(), first);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}